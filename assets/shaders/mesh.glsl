@@ -0,0 +1,22 @@
+#type vertex
+#version 330 core
+in vec3 position;
+in vec3 normal;
+out vec3 v_Normal;
+uniform mat4 u_ViewProjection;
+uniform mat4 u_Transform;
+void main() {
+    v_Normal = mat3(u_Transform) * normal;
+    gl_Position = u_ViewProjection * u_Transform * vec4(position, 1.0);
+}
+
+#type fragment
+#version 330 core
+in vec3 v_Normal;
+out vec4 color;
+uniform vec4 u_Color;
+void main() {
+    vec3 light_direction = normalize(vec3(0.4, 1.0, -0.6));
+    float diffuse = max(dot(normalize(v_Normal), light_direction), 0.0);
+    color = vec4(u_Color.rgb * (0.2 + 0.8 * diffuse), u_Color.a);
+}
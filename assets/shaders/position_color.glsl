@@ -0,0 +1,17 @@
+#type vertex
+#version 330 core
+in vec3 position;
+out vec3 v_Position;
+uniform mat4 u_ViewProjection;
+void main() {
+    v_Position = position;
+    gl_Position = u_ViewProjection * vec4(position, 1.0);
+}
+
+#type fragment
+#version 330 core
+out vec4 color;
+in vec3 v_Position;
+void main() {
+    color = vec4(v_Position * 0.5 + 0.5, 1.0);
+}
@@ -0,0 +1,49 @@
+#type vertex
+#version 330 core
+in vec3 position;
+in vec4 color;
+in vec2 tex_coord;
+in float tex_index;
+in float tiling_factor;
+out vec4 v_Color;
+out vec2 v_TexCoord;
+flat out float v_TexIndex;
+uniform mat4 u_ViewProjection;
+void main() {
+    v_Color = color;
+    v_TexCoord = tex_coord * tiling_factor;
+    v_TexIndex = tex_index;
+    gl_Position = u_ViewProjection * vec4(position, 1.0);
+}
+
+#type fragment
+#version 330 core
+in vec4 v_Color;
+in vec2 v_TexCoord;
+flat in float v_TexIndex;
+out vec4 color;
+uniform sampler2D u_Textures[16];
+void main() {
+    // glsl 330 only allows indexing sampler arrays with constants
+    // an index out of range draws like the white texture
+    vec4 tex_color = vec4(1.0);
+    switch (int(v_TexIndex)) {
+        case 0: tex_color = texture(u_Textures[0], v_TexCoord); break;
+        case 1: tex_color = texture(u_Textures[1], v_TexCoord); break;
+        case 2: tex_color = texture(u_Textures[2], v_TexCoord); break;
+        case 3: tex_color = texture(u_Textures[3], v_TexCoord); break;
+        case 4: tex_color = texture(u_Textures[4], v_TexCoord); break;
+        case 5: tex_color = texture(u_Textures[5], v_TexCoord); break;
+        case 6: tex_color = texture(u_Textures[6], v_TexCoord); break;
+        case 7: tex_color = texture(u_Textures[7], v_TexCoord); break;
+        case 8: tex_color = texture(u_Textures[8], v_TexCoord); break;
+        case 9: tex_color = texture(u_Textures[9], v_TexCoord); break;
+        case 10: tex_color = texture(u_Textures[10], v_TexCoord); break;
+        case 11: tex_color = texture(u_Textures[11], v_TexCoord); break;
+        case 12: tex_color = texture(u_Textures[12], v_TexCoord); break;
+        case 13: tex_color = texture(u_Textures[13], v_TexCoord); break;
+        case 14: tex_color = texture(u_Textures[14], v_TexCoord); break;
+        case 15: tex_color = texture(u_Textures[15], v_TexCoord); break;
+    }
+    color = tex_color * v_Color;
+}
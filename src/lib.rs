@@ -0,0 +1,5 @@
+// the engine as a library, so its public API is there for any application,
+// not only for what the editor in main.rs happens to call
+mod sequoia;
+
+pub use sequoia::*;
@@ -1,6 +1,8 @@
-use glium::glutin::{self, event_loop::ControlFlow};
+use glium::glutin;
 
-mod sequoia;
+// the log macros
+#[macro_use]
+extern crate sequoia;
 
 // macro_rules! debug {
 //     ($expression:expr) => {
@@ -10,23 +12,73 @@ mod sequoia;
 //     };
 // }
 use egui_dock::{DockArea, NodeIndex, Style, Tree};
+use sequoia::{camera_controller::EditorCameraMode, renderer::Camera};
 
-struct TabViewer;
+struct TabViewer {
+    viewport_texture: egui::TextureId,
+    viewport: Option<sequoia::renderer::Viewport>,
+    viewport_mouse: Option<(f64, f64)>,
+    mouse_world: Option<glam::Vec3>,
+    stats: sequoia::renderer2d::Statistics,
+    timestep: sequoia::timestep::Timestep,
+    show_3d: bool,
+    orbit: bool,
+    screenshot_viewport: bool,
+}
+
+impl TabViewer {
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        if ui.button("Screenshot viewport").clicked() {
+            self.screenshot_viewport = true;
+        }
+        ui.label("F12 takes a screenshot of the window");
+        ui.checkbox(&mut self.show_3d, "3D view");
+        if self.show_3d {
+            ui.checkbox(&mut self.orbit, "Orbit camera");
+            ui.label("Hold the right mouse button to look around");
+        }
+        match self.viewport_mouse {
+            Some((x, y)) => ui.label(format!("Viewport mouse: {x:.0}, {y:.0}")),
+            None => ui.label("Viewport mouse: outside"),
+        };
+        if let Some(world) = self.mouse_world {
+            ui.label(format!("World mouse: {:.2}, {:.2}", world.x, world.y));
+        }
+        ui.label(format!(
+            "Frame time: {:.2} ms ({:.0} FPS)",
+            self.timestep.milliseconds(),
+            self.timestep.fps()
+        ));
+        ui.label(format!("Draw calls: {}", self.stats.draw_calls));
+        ui.label(format!("Quads: {}", self.stats.quad_count));
+        ui.label(format!("Vertices: {}", self.stats.vertex_count()));
+    }
+}
 
 impl egui_dock::TabViewer for TabViewer {
     type Tab = String;
 
     fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Self::Tab) {
         if tab == "Viewport" {
-            // how can i send this to the renderer?
-            // let rect = ui.min_rect();
-            // info!("{:?}", rect);
+            // the framebuffer is rendered bottom up, so flip it vertically
+            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 1.0), egui::pos2(1.0, 0.0));
+            let size = ui.available_size();
+            let response = ui.add(egui::Image::new(self.viewport_texture, size).uv(uv));
+            self.viewport = Some(sequoia::renderer::Viewport {
+                rect: response.rect,
+                pixels_per_point: ui.ctx().pixels_per_point(),
+            });
         } else {
-            ui.label(format!("Content of {tab}"));
-            let rect = ui.min_rect();
             let g = 40;
-            ui.painter()
-                .rect_filled(rect, 0.0, egui::Color32::from_rgb(g, g, g));
+            egui::Frame::none()
+                .fill(egui::Color32::from_rgb(g, g, g))
+                .show(ui, |ui| {
+                    ui.set_min_size(ui.available_size());
+                    ui.label(format!("Content of {tab}"));
+                    if tab == "Settings" {
+                        self.settings_ui(ui);
+                    }
+                });
         }
     }
 
@@ -36,30 +88,47 @@ impl egui_dock::TabViewer for TabViewer {
 }
 
 struct ExampleLayer {
+    display: glium::Display,
     tree: Tree<String>,
+    framebuffer: sequoia::renderer::Framebuffer,
+    viewport: Option<sequoia::renderer::Viewport>,
+    viewport_mouse: Option<(f64, f64)>,
+    mouse_world: Option<glam::Vec3>,
+    renderer_2d: sequoia::renderer2d::Renderer2D,
+    checkerboard: sequoia::texture::Texture2D,
     vertex_array: sequoia::renderer::VertexArray,
-    program: glium::program::Program,
-    camera: sequoia::renderer::OrthographicCamera,
-    camera_position: glam::Vec3,
-    camera_speed: f32,
+    shader_library: sequoia::shader::ShaderLibrary,
+    camera_controller: sequoia::camera_controller::OrthographicCameraController,
+    show_3d: bool,
+    input_map: sequoia::input_map::InputMap,
+    editor_camera: sequoia::camera_controller::EditorCameraController,
+    cube: sequoia::mesh::Mesh,
+    timestep: sequoia::timestep::Timestep,
+    // simulated in on_fixed_update, interpolated when drawn
+    spin: f32,
+    previous_spin: f32,
 }
 
 impl ExampleLayer {
-    pub fn new(display: &glium::Display) -> Self {
+    pub fn new(display: &glium::Display) -> Result<Self, sequoia::shader::ShaderError> {
         let mut tree = Tree::new(vec!["Viewport".to_owned()]);
 
         // You can modify the tree before constructing the dock
         let [a, _] = tree.split_right(NodeIndex::root(), 0.75, vec!["Settings".to_owned()]);
         let [_, _] = tree.split_left(a, 0.25, vec!["Console".to_owned()]);
 
-        let shape = vec![
-            sequoia::renderer::Vertex::from([-0.5, -0.5, 0.0]),
-            sequoia::renderer::Vertex::from([0.5, -0.5, 0.0]),
-            sequoia::renderer::Vertex::from([0.5, 0.5, 0.0]),
-            sequoia::renderer::Vertex::from([-0.5, 0.5, 0.0]),
+        let layout = sequoia::buffer::BufferLayout::new(vec![sequoia::buffer::BufferElement::new(
+            sequoia::buffer::ShaderDataType::Float3,
+            "position",
+        )]);
+        let shape: [[f32; 3]; 4] = [
+            [-0.5, -0.5, 0.0],
+            [0.5, -0.5, 0.0],
+            [0.5, 0.5, 0.0],
+            [-0.5, 0.5, 0.0],
         ];
 
-        let vertex_buffer = glium::VertexBuffer::new(display, &shape).unwrap();
+        let vertex_buffer = sequoia::buffer::VertexBuffer::new(display, layout, &shape).unwrap();
         let index_buffer = glium::IndexBuffer::new(
             display,
             glium::index::PrimitiveType::TrianglesList,
@@ -72,152 +141,264 @@ impl ExampleLayer {
             index_buffer,
         };
 
-        let vertex_shader_src = r#"
-            #version 330 core
-            in vec3 position;
-            out vec3 v_Position;
-            uniform mat4 u_ViewProjection;
-            void main() {
-                v_Position = position;
-                gl_Position = u_ViewProjection * vec4(position, 1.0);
-            }
-        "#;
-
-        let fragment_shader_src = r#"
-            #version 330 core
-            out vec4 color;
-            in vec3 v_Position;
-            void main() {
-                color = vec4(v_Position * 0.5 + 0.5, 1.0);
-            }
-        "#;
-        let program =
-            glium::Program::from_source(display, vertex_shader_src, fragment_shader_src, None)
-                .unwrap();
-        let camera_position = glam::vec3(0.0, 0.0, 0.0);
-        let camera =
-            sequoia::renderer::OrthographicCamera::new(-1.6, 1.6, -0.9, 0.9, camera_position, 0.0);
-
-        Self {
+        let mut shader_library = sequoia::shader::ShaderLibrary::new();
+        shader_library.load(display, "assets/shaders/position_color.glsl")?;
+        shader_library.load(display, "assets/shaders/mesh.glsl")?;
+
+        let checkerboard_pixels: Vec<u8> = (0..8 * 8)
+            .flat_map(|i| {
+                let g = if (i % 8 + i / 8) % 2 == 0 { 200 } else { 120 };
+                [g, g, g, 255]
+            })
+            .collect();
+        let checkerboard = sequoia::texture::Texture2D::from_rgba(
+            display,
+            8,
+            8,
+            &checkerboard_pixels,
+            sequoia::texture::TextureSettings {
+                filter: sequoia::texture::TextureFilter::Nearest,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let (width, height) = display.get_framebuffer_dimensions();
+        let framebuffer = sequoia::renderer::Framebuffer::new(display, width, height);
+        let camera_controller = sequoia::camera_controller::OrthographicCameraController::new(
+            width as f32 / height as f32,
+            true,
+        );
+        let editor_camera = sequoia::camera_controller::EditorCameraController::new(
+            width as f32 / height as f32,
+            EditorCameraMode::Orbit,
+        );
+
+        let mut input_map = sequoia::input_map::InputMap::new();
+        input_map.add_context(
+            "example",
+            sequoia::input_map::InputContext::new().with_action(
+                "toggle_3d",
+                sequoia::input_map::Button::Key(sequoia::keycode::KeyCode::Tab),
+            ),
+        );
+        input_map.push_context("example");
+        // players can rebind keys in input.cfg, see InputMap for the format
+        match input_map.load("input.cfg") {
+            Err(sequoia::input_map::InputMapError::Io(why))
+                if why.kind() == std::io::ErrorKind::NotFound => {}
+            Err(why) => warn!("Could not load input.cfg: {}", why),
+            Ok(_) => info!("Loaded bindings from input.cfg"),
+        }
+
+        Ok(Self {
+            display: display.clone(),
             tree,
+            framebuffer,
+            viewport: None,
+            viewport_mouse: None,
+            mouse_world: None,
+            renderer_2d: sequoia::renderer2d::Renderer2D::new(display)?,
+            checkerboard,
             vertex_array,
-            program,
-            camera,
-            camera_position,
-            camera_speed: 0.01,
-        }
+            shader_library,
+            camera_controller,
+            show_3d: false,
+            input_map,
+            editor_camera,
+            cube: sequoia::mesh::Mesh::cube(display).unwrap(),
+            timestep: Default::default(),
+            spin: 0.0,
+            previous_spin: 0.0,
+        })
     }
 }
 
 impl sequoia::layer::Layer for ExampleLayer {
-    fn on_event(&mut self, event: &mut Option<sequoia::event::Event>) {
-        if let Some(e) = event {
-            match e {
-                sequoia::event::Event::KeyPress { key } => match key {
-                    _ => {}
-                },
-                _ => {}
-            }
+    fn on_event(&mut self, event: &sequoia::event::Event) -> bool {
+        // only zoom while hovering the viewport
+        if self.viewport_mouse.is_none() {
+            return false;
+        }
+        if self.show_3d {
+            self.editor_camera.on_event(event)
+        } else {
+            self.camera_controller.on_event(event)
         }
     }
 
-    fn on_update(&mut self, target: &mut glium::Frame, input: &sequoia::input::Input) {
+    fn on_fixed_update(
+        &mut self,
+        _input: &sequoia::input::Input,
+        timestep: sequoia::timestep::Timestep,
+    ) {
+        self.previous_spin = self.spin;
+        self.spin += 45.0_f32.to_radians() * timestep.seconds();
+    }
+
+    fn on_update(
+        &mut self,
+        target: &mut glium::Frame,
+        input: &sequoia::input::Input,
+        timestep: sequoia::timestep::Timestep,
+    ) {
+        self.timestep = timestep;
+        self.shader_library.reload_changed(&self.display);
+
+        if self
+            .input_map
+            .was_action_pressed_this_frame(input, "toggle_3d")
         {
-            use sequoia::keycode::KeyCode;
-            if input.keys_pressed.contains(&KeyCode::A) {
-                self.camera_position.x += self.camera_speed;
-            }
-            if input.keys_pressed.contains(&KeyCode::D) {
-                self.camera_position.x -= self.camera_speed;
-            }
-            if input.keys_pressed.contains(&KeyCode::W) {
-                self.camera_position.y -= self.camera_speed;
-            }
-            if input.keys_pressed.contains(&KeyCode::S) {
-                self.camera_position.y += self.camera_speed;
-            }
+            self.show_3d = !self.show_3d;
+        }
+        if self.show_3d {
+            self.editor_camera.on_update(input, timestep);
+        } else {
+            self.camera_controller.on_update(input, timestep);
         }
 
-        sequoia::renderer::Renderer::set_clear_color(target, glam::vec4(0.1, 0.1, 0.1, 1.0));
-        self.camera.set_position(self.camera_position);
-        // self.camera.set_rotation(45.0);
+        self.viewport_mouse = self
+            .viewport
+            .and_then(|viewport| viewport.local_position(input.mouse_location));
+        self.mouse_world = match (self.viewport, self.viewport_mouse) {
+            (Some(viewport), Some(_)) => Some(
+                self.camera_controller
+                    .camera()
+                    .screen_to_world(input.mouse_location, &viewport),
+            ),
+            _ => None,
+        };
+
+        sequoia::renderer::Renderer::set_clear_color(target, glam::vec4(0.0, 0.0, 0.0, 1.0));
+
+        let mut viewport_target = self.framebuffer.surface();
+        sequoia::renderer::Renderer::set_clear_color(
+            &mut viewport_target,
+            glam::vec4(0.1, 0.1, 0.1, 1.0),
+        );
+
+        if self.show_3d {
+            let shader = self.shader_library.get_mut("mesh").unwrap();
+            let view_projection_matrix = self.editor_camera.camera().view_projection_matrix();
+            for (i, color) in [
+                glam::vec4(0.8, 0.2, 0.3, 1.0),
+                glam::vec4(0.2, 0.8, 0.3, 1.0),
+                glam::vec4(0.2, 0.3, 0.8, 1.0),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                shader.set_float4("u_Color", color);
+                let transform =
+                    glam::Mat4::from_translation(glam::vec3(i as f32 * 1.5 - 1.5, 0.0, 0.0))
+                        * glam::Mat4::from_rotation_y(i as f32 * 0.4 + timestep.elapsed() as f32);
+                sequoia::renderer::Renderer::draw_mesh(
+                    &mut viewport_target,
+                    shader,
+                    &self.cube,
+                    view_projection_matrix,
+                    transform,
+                );
+            }
+            return;
+        }
 
         // draw the square
         // in the future, program and vertex array are different per shape
         sequoia::renderer::Renderer::draw_indexed(
-            target,
-            &self.program,
+            &mut viewport_target,
+            self.shader_library.get("position_color").unwrap(),
             &self.vertex_array,
-            self.camera.view_projection_matrix(),
+            self.camera_controller.camera().view_projection_matrix(),
         );
+
+        self.renderer_2d.begin_scene(self.camera_controller.camera());
+        self.renderer_2d.draw_textured_quad(
+            glam::vec3(0.0, 0.0, 0.0),
+            glam::vec2(4.0, 4.0),
+            &self.checkerboard,
+            4.0,
+            glam::vec4(1.0, 0.9, 0.9, 1.0),
+        );
+        for y in 0..20 {
+            for x in 0..20 {
+                let position = glam::vec3(x as f32 * 0.11 - 1.0, y as f32 * 0.11 - 1.0, 0.0);
+                let color = glam::vec4(x as f32 / 20.0, 0.4, y as f32 / 20.0, 0.5);
+                self.renderer_2d.draw_quad(position, glam::vec2(0.1, 0.1), color);
+            }
+        }
+        self.renderer_2d.draw_rotated_quad(
+            glam::vec3(1.0, 0.5, 0.0),
+            glam::vec2(0.5, 0.5),
+            self.previous_spin + (self.spin - self.previous_spin) * timestep.alpha(),
+            glam::vec4(0.8, 0.2, 0.3, 1.0),
+        );
+        self.renderer_2d.end_scene(&mut viewport_target);
     }
 
     fn on_egui_render(
         &mut self,
+        egui_ctx: &egui::Context,
+        painter: &mut egui_glium::Painter,
         _input: &sequoia::input::Input,
-        display: &glium::Display,
-        target: &mut glium::Frame,
-        event: &glium::glutin::event::Event<()>,
-        control_flow: &mut glium::glutin::event_loop::ControlFlow,
-        egui_glium: &mut egui_glium::EguiGlium,
     ) {
-        let repaint_after = egui_glium.run(display, |egui_ctx| {
-            let mut s = Style::from_egui(egui_ctx.style().as_ref());
-            s.default_inner_margin = egui::style::Margin::default();
-            s.tab_background_color = egui::Color32::TRANSPARENT;
-            DockArea::new(&mut self.tree)
-                .style(s)
-                .show(egui_ctx, &mut TabViewer);
-            // egui::SidePanel::left("side_panel").show(egui_ctx, |ui| {
-            //     ui.visuals_mut().window_fill = egui::Color32::RED;
-            //     ui.visuals_mut().panel_fill = egui::Color32::DARK_RED;
-            //     ui.visuals_mut().code_bg_color = egui::Color32::DARK_GREEN;
-            //     ui.visuals_mut().faint_bg_color = egui::Color32::GREEN;
-            //     ui.visuals_mut().extreme_bg_color = egui::Color32::LIGHT_GREEN;
-            //     ui.heading("Sequoia");
-            //     if ui.button("Click me").clicked() {
-            //         if input
-            //             .keys_pressed
-            //             .contains(&sequoia::keycode::KeyCode::LeftShift)
-            //         {
-            //             info!("You are holding left shift");
-            //         } else {
-            //             info!("You are not holding left shift");
-            //         }
-            //     }
-            // });
-        });
-
-        *control_flow = if repaint_after.is_zero() {
-            display.gl_window().window().request_redraw();
-            ControlFlow::Poll
-        } else if let Some(repaint_after_instant) =
-            std::time::Instant::now().checked_add(repaint_after)
-        {
-            ControlFlow::WaitUntil(repaint_after_instant)
-        } else {
-            ControlFlow::Wait
+        let mut tab_viewer = TabViewer {
+            viewport_texture: self.framebuffer.texture_id(painter),
+            viewport: None,
+            viewport_mouse: self.viewport_mouse,
+            mouse_world: self.mouse_world,
+            stats: self.renderer_2d.stats(),
+            timestep: self.timestep,
+            show_3d: self.show_3d,
+            orbit: self.editor_camera.mode() == EditorCameraMode::Orbit,
+            screenshot_viewport: false,
         };
+        let mut s = Style::from_egui(egui_ctx.style().as_ref());
+        s.default_inner_margin = egui::style::Margin::default();
+        s.tab_background_color = egui::Color32::TRANSPARENT;
+        DockArea::new(&mut self.tree)
+            .style(s)
+            .show(egui_ctx, &mut tab_viewer);
+        // egui::SidePanel::left("side_panel").show(egui_ctx, |ui| {
+        //     ui.visuals_mut().window_fill = egui::Color32::RED;
+        //     ui.visuals_mut().panel_fill = egui::Color32::DARK_RED;
+        //     ui.visuals_mut().code_bg_color = egui::Color32::DARK_GREEN;
+        //     ui.visuals_mut().faint_bg_color = egui::Color32::GREEN;
+        //     ui.visuals_mut().extreme_bg_color = egui::Color32::LIGHT_GREEN;
+        //     ui.heading("Sequoia");
+        //     if ui.button("Click me").clicked() {
+        //         if input
+        //             .keys_pressed
+        //             .contains(&sequoia::keycode::KeyCode::LeftShift)
+        //         {
+        //             info!("You are holding left shift");
+        //         } else {
+        //             info!("You are not holding left shift");
+        //         }
+        //     }
+        // });
 
-        egui_glium.paint(display, target);
-
-        match event {
-            // Platform-dependent event handlers to workaround a winit bug
-            // See: https://github.com/rust-windowing/winit/issues/987
-            // See: https://github.com/rust-windowing/winit/issues/1619
-            // glutin::event::Event::RedrawEventsCleared if cfg!(windows) => redraw(),
-            // glutin::event::Event::RedrawRequested(_) if !cfg!(windows) => redraw(),
-            glutin::event::Event::WindowEvent { event, .. } => {
-                let event_response = egui_glium.on_event(&event);
-
-                if event_response.repaint {
-                    display.gl_window().window().request_redraw();
-                }
+        // the scene is rendered before the ui, so the new size applies next frame
+        self.viewport = tab_viewer.viewport;
+        self.show_3d = tab_viewer.show_3d;
+        self.editor_camera.set_mode(if tab_viewer.orbit {
+            EditorCameraMode::Orbit
+        } else {
+            EditorCameraMode::Fly
+        });
+        if tab_viewer.screenshot_viewport {
+            sequoia::capture::save_png(
+                self.framebuffer.read_pixels(),
+                format!("screenshots/viewport_{}.png", sequoia::capture::timestamp()).into(),
+            );
+        }
+        if let Some(viewport) = self.viewport {
+            let (width, height) = viewport.size_in_pixels();
+            if self.framebuffer.size() != (width, height) {
+                self.framebuffer.resize(width, height);
+                self.camera_controller.on_resize(width, height);
+                self.editor_camera.on_resize(width, height);
             }
-            glutin::event::Event::NewEvents(glutin::event::StartCause::ResumeTimeReached {
-                ..
-            }) => display.gl_window().window().request_redraw(),
-            _ => (),
         }
     }
 }
@@ -225,17 +406,26 @@ impl sequoia::layer::Layer for ExampleLayer {
 fn main() {
     sequoia::log::init();
 
-    let event_loop = glium::glutin::event_loop::EventLoop::new();
+    let event_loop = glutin::event_loop::EventLoopBuilder::with_user_event().build();
 
     let size = glutin::dpi::LogicalSize::new(50.0 * 16.0, 50.0 * 9.0);
     let wb = glutin::window::WindowBuilder::new()
         .with_title("Sequoia")
         .with_inner_size(size);
-    let cb = glutin::ContextBuilder::new();
+    // vsync has to be chosen when the display is created
+    let pacing = sequoia::frame_pacing::FramePacing::VSync;
+    let cb = pacing.context_builder();
     let display = glium::Display::new(wb, cb, &event_loop).unwrap();
 
-    let example_layer = Box::new(ExampleLayer::new(&display));
+    let example_layer = match ExampleLayer::new(&display) {
+        Ok(example_layer) => Box::new(example_layer),
+        Err(why) => {
+            error!("{}", why);
+            return;
+        }
+    };
     let mut app = Box::new(sequoia::application::Application::new(display, &event_loop));
+    app.set_frame_pacing(pacing);
     app.push_layer(example_layer);
     app.run(event_loop);
 }
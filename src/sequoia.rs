@@ -2,10 +2,24 @@
 #[macro_use]
 pub mod log;
 pub mod application;
+pub mod buffer;
+pub mod camera_controller;
+pub mod capture;
 pub mod event;
+pub mod frame_pacing;
 pub mod input;
+pub mod input_map;
+#[macro_use]
 pub mod keycode;
 pub mod layer;
+pub mod mesh;
 pub mod mousecode;
+pub mod physical_key;
 pub mod renderer;
+pub mod renderer2d;
+pub mod shader;
+#[cfg(test)]
+pub mod snapshot;
+pub mod texture;
+pub mod timestep;
 
@@ -1,74 +1,337 @@
-use std::collections::HashSet;
+use std::{collections::HashMap, fmt, rc::Rc, sync::mpsc, time::Duration};
 
-use glium::glutin;
+use glium::{backend::Facade, glutin};
 
-use super::{event, input, keycode, layer, mousecode};
+use super::{
+    capture, event,
+    frame_pacing::{FramePacer, FramePacing},
+    input, keycode, layer, mousecode, physical_key, timestep,
+};
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    Context(glutin::CreationError),
+    Incompatible(glium::IncompatibleOpenGl),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadlessError::Context(why) => write!(f, "could not create headless context: {}", why),
+            HeadlessError::Incompatible(why) => write!(f, "unsupported OpenGL context: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+impl From<glutin::CreationError> for HeadlessError {
+    fn from(why: glutin::CreationError) -> Self {
+        HeadlessError::Context(why)
+    }
+}
+
+impl From<glium::IncompatibleOpenGl> for HeadlessError {
+    fn from(why: glium::IncompatibleOpenGl) -> Self {
+        HeadlessError::Incompatible(why)
+    }
+}
+
+/// Where frames are drawn to.
+enum Backend {
+    Window {
+        display: glium::Display,
+        egui_glium: Box<egui_glium::EguiGlium>,
+    },
+    /// An offscreen context, driven by `run_frames` instead of an event loop.
+    Headless {
+        renderer: glium::HeadlessRenderer,
+        width: u32,
+        height: u32,
+        elapsed: Duration,
+        egui_ctx: egui::Context,
+        painter: Box<egui_glium::Painter>,
+    },
+}
+
+/// The keys held down, by the scancode they were pressed with, so a key is
+/// released as the key it was pressed as, even if the layout changed in
+/// between. Otherwise it would stay pressed forever.
+#[derive(Debug, Default)]
+struct PressedKeys(HashMap<glutin::event::ScanCode, keycode::KeyCode>);
+
+impl PressedKeys {
+    /// The key press or release of `input`. Presses are never repeats here,
+    /// since that is up to the input state.
+    fn on_input(
+        &mut self,
+        input: &glutin::event::KeyboardInput,
+        modifiers: keycode::Modifiers,
+    ) -> event::Event {
+        let physical_key = physical_key::PhysicalKey::from_scancode(input.scancode);
+        match input.state {
+            glutin::event::ElementState::Pressed => {
+                let key = keycode::KeyCode::from_input(input);
+                self.0.insert(input.scancode, key);
+                event::Event::KeyPress(event::KeyPress {
+                    key,
+                    physical_key,
+                    modifiers,
+                    repeat: false,
+                })
+            }
+            glutin::event::ElementState::Released => {
+                let key = self
+                    .0
+                    .remove(&input.scancode)
+                    .unwrap_or_else(|| keycode::KeyCode::from_input(input));
+                event::Event::KeyRelease(event::KeyRelease { key, physical_key })
+            }
+        }
+    }
+
+    /// Forgets all keys, like when the window loses focus and won't hear
+    /// about their releases.
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// Whether egui keeps `event` to itself, when it `consumed` it. That is
+/// only the case for keys and text typed into a focused egui widget. Key
+/// releases always go on, so keys pressed before the widget took focus are
+/// released. Pointer events go on too, since the scene is drawn in an egui
+/// panel that wants the pointer as well.
+fn egui_keeps(event: &glutin::event::WindowEvent, consumed: bool) -> bool {
+    consumed
+        && match event {
+            glutin::event::WindowEvent::KeyboardInput { input, .. } => {
+                input.state == glutin::event::ElementState::Pressed
+            }
+            glutin::event::WindowEvent::ReceivedCharacter(_)
+            | glutin::event::WindowEvent::Ime(_) => true,
+            _ => false,
+        }
+}
 
 pub struct Application {
-    egui_glium: egui_glium::EguiGlium,
+    backend: Backend,
     layer_stack: layer::LayerStack,
-    display: glium::Display,
     input: input::Input,
+    clock: timestep::Clock,
+    fixed_timestep: timestep::FixedTimestep,
+    frame_pacer: FramePacer,
+    event_queue: event::EventQueue,
+    event_proxy: event::EventProxy,
+    /// User events sent through `event_proxy` without an event loop.
+    user_events: Option<mpsc::Receiver<event::UserEvent>>,
+    frame_capture: capture::FrameCapture,
+    screenshot_key: Option<keycode::KeyCode>,
+    /// Nothing is drawn while the window is minimized.
+    minimized: bool,
+    /// The modifiers as of the latest window event, for new key presses.
+    modifiers: keycode::Modifiers,
+    pressed_keys: PressedKeys,
 }
 
 impl Application {
-    pub fn new(display: glium::Display, event_loop: &glutin::event_loop::EventLoop<()>) -> Self {
+    /// Creates an application drawing to `display`. The event loop carries
+    /// `UserEvent`s, so create it with
+    /// `EventLoopBuilder::with_user_event().build()`.
+    pub fn new(
+        display: glium::Display,
+        event_loop: &glutin::event_loop::EventLoop<event::UserEvent>,
+    ) -> Self {
+        let egui_glium = Box::new(egui_glium::EguiGlium::new(&display, event_loop));
+        // for TextInput from input methods
+        display.gl_window().window().set_ime_allowed(true);
+        Self::with_backend(
+            Backend::Window {
+                display,
+                egui_glium,
+            },
+            event_loop.create_proxy().into(),
+            None,
+        )
+    }
+
+    /// Creates an application without a window, for tests. It draws into an
+    /// OSMesa context, which software Mesa provides on machines without a GPU
+    /// or display server. Drive it with `post_event` and `run_frames`.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    pub fn new_headless(width: u32, height: u32) -> Result<Self, HeadlessError> {
+        use glutin::platform::unix::HeadlessContextExt;
 
-        let egui_glium = egui_glium::EguiGlium::new(&display, event_loop);
+        let context = glutin::ContextBuilder::new()
+            .build_osmesa(glutin::dpi::PhysicalSize::new(width, height))?;
+        Self::with_headless_context(context, width, height)
+    }
+
+    /// Like `new_headless`, with any headless context, such as one from
+    /// `HeadlessContextExt::build_surfaceless` for EGL.
+    pub fn with_headless_context(
+        context: glutin::Context<glutin::NotCurrent>,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, HeadlessError> {
+        let renderer = glium::HeadlessRenderer::new(context)?;
+        let painter = Box::new(egui_glium::Painter::new(&renderer));
+        let (sender, receiver) = mpsc::channel();
+        Ok(Self::with_backend(
+            Backend::Headless {
+                renderer,
+                width,
+                height,
+                elapsed: Duration::ZERO,
+                egui_ctx: egui::Context::default(),
+                painter,
+            },
+            sender.into(),
+            Some(receiver),
+        ))
+    }
+
+    fn with_backend(
+        backend: Backend,
+        event_proxy: event::EventProxy,
+        user_events: Option<mpsc::Receiver<event::UserEvent>>,
+    ) -> Self {
         Self {
-            egui_glium,
+            backend,
             layer_stack: layer::LayerStack::new(),
-            display,
-            input: input::Input {
-                keys_pressed: HashSet::new(),
-                mouse_pressed: HashSet::new(),
-                mouse_location: (0.0, 0.0),
-            },
+            input: input::Input::new(),
+            clock: timestep::Clock::new(),
+            fixed_timestep: timestep::FixedTimestep::new(60.0),
+            frame_pacer: FramePacer::new(FramePacing::default()),
+            event_queue: event::EventQueue::new(),
+            event_proxy,
+            user_events,
+            frame_capture: capture::FrameCapture::new("screenshots"),
+            screenshot_key: Some(keycode::KeyCode::F12),
+            minimized: false,
+            modifiers: keycode::Modifiers::NONE,
+            pressed_keys: PressedKeys::default(),
         }
     }
 
-    pub fn on_event(&mut self, mut event: Option<event::Event>) {
-        if let Some(e) = event {
-            match e {
-                event::Event::WindowClose => {
-                    debug!("Closing window.");
-                    event = None;
-                }
-                event::Event::WindowResize { width, height } => {
-                    debug!("WindowResize {}, {}", width, height);
-                    event = None;
-                }
-                event::Event::KeyPress { key } => {
-                    // debug!("KeyPress {:?}", key);
-                    self.input.keys_pressed.insert(key);
-                }
-                event::Event::KeyRelease { key } => {
-                    // debug!("KeyRelease {:?}", key);
-                    self.input.keys_pressed.remove(&key);
-                }
-                event::Event::MouseButtonPress { mouse_button } => {
-                    // debug!("MouseButtonPress {:?}", mouse_button);
-                    self.input.mouse_pressed.insert(mouse_button);
-                }
-                event::Event::MouseButtonRelease { mouse_button } => {
-                    // debug!("MouseButtonRelease {:?}", mouse_button);
-                    self.input.mouse_pressed.remove(&mouse_button);
-                }
-                // event::Event::MouseScroll { x, y } => debug!("MouseScroll {}, {}", x, y),
-                event::Event::MouseMove { x, y } => {
-                    // debug!("MouseMove {}, {}", x, y);
-                    self.input.mouse_location = (x, y)
-                }
-                _ => {}
+    /// The OpenGL context, for creating textures, shaders and buffers in
+    /// either mode.
+    pub fn context(&self) -> Rc<glium::backend::Context> {
+        match &self.backend {
+            Backend::Window { display, .. } => display.get_context().clone(),
+            Backend::Headless { renderer, .. } => renderer.get_context().clone(),
+        }
+    }
+
+    /// Queues an event as if it came from the window. Use it to send
+    /// synthetic events to a headless application.
+    pub fn post_event(&mut self, event: impl Into<event::Event>) {
+        self.event_queue.post(event);
+    }
+
+    /// A handle to the event queue, for layers that post their own events.
+    pub fn event_queue(&self) -> event::EventQueue {
+        self.event_queue.clone()
+    }
+
+    /// Sends user events to the layers from any thread, like a job
+    /// announcing that it finished loading.
+    pub fn event_proxy(&self) -> event::EventProxy {
+        self.event_proxy.clone()
+    }
+
+    /// Dispatches all queued events, oldest first.
+    fn dispatch_events(&mut self) {
+        if let Some(user_events) = &self.user_events {
+            for user_event in user_events.try_iter() {
+                self.event_queue.post(user_event);
             }
         }
+        for event in self.event_queue.take() {
+            self.on_event(event);
+        }
+    }
+
+    /// Updates the input state with `event`, then sends it to the layers.
+    fn on_event(&mut self, mut event: event::Event) {
+        if let event::Event::KeyPress(press) = &mut event {
+            press.repeat = self
+                .input
+                .physical_keys_pressed
+                .contains(&press.physical_key);
+        }
 
-        for layer in self.layer_stack.iter_mut().rev() {
-            match event {
-                None => break,
-                Some(_) => layer.on_event(&mut event),
+        match event {
+            event::Event::WindowClose(_) => {
+                debug!("Closing window.");
+            }
+            event::Event::WindowResize(event::WindowResize { width, height }) => {
+                debug!("WindowResize {}, {}", width, height);
             }
+            // only on the first press, not while the key repeats
+            event::Event::KeyPress(event::KeyPress {
+                key, repeat: false, ..
+            }) if self.screenshot_key == Some(key) => self.screenshot(),
+            // event::Event::MouseScroll(event::MouseScroll { x, y }) => debug!("MouseScroll {}, {}", x, y),
+            _ => {}
         }
+
+        self.input.on_event(&event);
+        self.layer_stack.on_event(&event);
+    }
+
+    /// Sets how many times per second `Layer::on_fixed_update` runs. The
+    /// default is 60, and the rate is clamped to `FixedTimestep::MIN_RATE`
+    /// and `FixedTimestep::MAX_RATE`.
+    pub fn set_fixed_update_rate(&mut self, rate: f64) {
+        self.fixed_timestep.set_rate(rate);
+    }
+
+    /// Sets how many fixed updates a slow frame may run to catch up. The
+    /// default is 5.
+    pub fn set_max_fixed_updates_per_frame(&mut self, max_steps: u32) {
+        self.fixed_timestep.set_max_steps_per_frame(max_steps);
+    }
+
+    /// Sets when frames are drawn. The default is `Capped` at 60 frames per
+    /// second. Layers can't change it, so it always holds. `VSync` only
+    /// works if the display was created with `FramePacing::context_builder`.
+    pub fn set_frame_pacing(&mut self, pacing: FramePacing) {
+        self.frame_pacer.set_pacing(pacing);
+    }
+
+    pub fn frame_pacing(&self) -> FramePacing {
+        self.frame_pacer.pacing()
+    }
+
+    /// Saves the next frame, ui included, as a timestamped PNG in the capture
+    /// directory.
+    pub fn screenshot(&mut self) {
+        self.frame_capture.request_screenshot();
+    }
+
+    /// Saves the next `frames` frames as a numbered PNG sequence in a new
+    /// folder of the capture directory.
+    pub fn record_frames(&mut self, frames: u32) {
+        self.frame_capture.record(frames);
+    }
+
+    /// Sets where screenshots and recordings go. The default is
+    /// `screenshots` in the working directory.
+    pub fn set_capture_directory(&mut self, directory: impl Into<std::path::PathBuf>) {
+        self.frame_capture.set_directory(directory);
+    }
+
+    /// Sets the key that takes a screenshot, or `None` to only take them with
+    /// `screenshot`. The default is F12.
+    pub fn set_screenshot_key(&mut self, key: Option<keycode::KeyCode>) {
+        self.screenshot_key = key;
     }
 
     pub fn push_layer(&mut self, mut layer: Box<dyn layer::Layer>) {
@@ -93,109 +356,523 @@ impl Application {
 
     fn handle_event(
         &mut self,
-        ev: glutin::event::Event<()>,
+        ev: glutin::event::Event<event::UserEvent>,
+        egui_consumed: bool,
         control_flow: &mut glutin::event_loop::ControlFlow,
     ) {
         match ev {
+            glutin::event::Event::WindowEvent { event, .. }
+                if egui_keeps(&event, egui_consumed) => {}
             glutin::event::Event::WindowEvent { event, .. } => match event {
                 glutin::event::WindowEvent::CloseRequested => {
                     *control_flow = glutin::event_loop::ControlFlow::Exit;
-                    self.on_event(Some(event::Event::WindowClose));
+                    // no frame runs after exiting, so layers hear about it now
+                    self.post_event(event::WindowClose);
+                    self.dispatch_events();
                 }
                 glutin::event::WindowEvent::Resized(size) => {
-                    self.on_event(Some(event::Event::WindowResize {
-                        width: size.width,
-                        height: size.height,
-                    }));
-                }
-                glutin::event::WindowEvent::KeyboardInput { input, .. } => match input.state {
-                    glutin::event::ElementState::Pressed => {
-                        self.on_event(Some(event::Event::KeyPress {
-                            key: keycode::KeyCode::convert(
-                                input
-                                    .virtual_keycode
-                                    .unwrap_or(glutin::event::VirtualKeyCode::NumpadDivide),
-                            ),
-                        }));
+                    // minimizing shrinks the window to nothing on Windows,
+                    // and the next resize restores it. winit can't tell
+                    // otherwise, so elsewhere the window is never minimized.
+                    let minimized = size.width == 0 || size.height == 0;
+                    if minimized != self.minimized {
+                        self.minimized = minimized;
+                        if minimized {
+                            // no frame runs until it is restored
+                            self.post_event(event::WindowMinimize);
+                            self.dispatch_events();
+                        } else {
+                            self.clock.resume();
+                            self.post_event(event::WindowRestore);
+                        }
                     }
-                    glutin::event::ElementState::Released => {
-                        self.on_event(Some(event::Event::KeyRelease {
-                            key: keycode::KeyCode::convert(
-                                input
-                                    .virtual_keycode
-                                    .unwrap_or(glutin::event::VirtualKeyCode::Space),
-                            ),
-                        }));
+                    if !minimized {
+                        self.post_event(event::WindowResize {
+                            width: size.width,
+                            height: size.height,
+                        });
                     }
-                },
+                }
+                glutin::event::WindowEvent::Moved(position) => {
+                    self.post_event(event::WindowMove {
+                        x: position.x,
+                        y: position.y,
+                    });
+                }
+                glutin::event::WindowEvent::Focused(true) => self.post_event(event::WindowFocus),
+                glutin::event::WindowEvent::Focused(false) => {
+                    // the input state forgets these keys too
+                    self.pressed_keys.clear();
+                    self.post_event(event::WindowUnfocus);
+                }
+                glutin::event::WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                    self.post_event(event::WindowRescale { scale_factor });
+                }
+                glutin::event::WindowEvent::DroppedFile(path) => {
+                    self.post_event(event::FileDrop { path });
+                }
+                glutin::event::WindowEvent::HoveredFile(path) => {
+                    self.post_event(event::FileHover { path });
+                }
+                glutin::event::WindowEvent::HoveredFileCancelled => {
+                    self.post_event(event::FileHoverCancel);
+                }
+                glutin::event::WindowEvent::KeyboardInput { input, .. } => {
+                    let event = self.pressed_keys.on_input(&input, self.modifiers);
+                    self.post_event(event);
+                }
                 glutin::event::WindowEvent::MouseInput { state, button, .. } => match state {
                     glutin::event::ElementState::Pressed => {
-                        self.on_event(Some(event::Event::MouseButtonPress {
+                        self.post_event(event::Event::MouseButtonPress(event::MouseButtonPress {
                             mouse_button: mousecode::MouseCode::convert(button),
                         }));
                     }
                     glutin::event::ElementState::Released => {
-                        self.on_event(Some(event::Event::MouseButtonRelease {
-                            mouse_button: mousecode::MouseCode::convert(button),
-                        }));
+                        self.post_event(event::Event::MouseButtonRelease(
+                            event::MouseButtonRelease {
+                                mouse_button: mousecode::MouseCode::convert(button),
+                            },
+                        ));
                     }
                 },
                 glutin::event::WindowEvent::MouseWheel { delta, .. } => match delta {
                     glutin::event::MouseScrollDelta::PixelDelta(pos) => {
-                        self.on_event(Some(event::Event::MouseScroll { x: pos.x, y: pos.y }));
+                        self.post_event(event::Event::MouseScroll(event::MouseScroll {
+                            x: pos.x,
+                            y: pos.y,
+                        }));
                     }
                     glutin::event::MouseScrollDelta::LineDelta(x, y) => {
-                        self.on_event(Some(event::Event::MouseScroll {
+                        self.post_event(event::Event::MouseScroll(event::MouseScroll {
                             x: x as f64,
                             y: y as f64,
                         }));
                     }
                 },
                 glutin::event::WindowEvent::CursorMoved { position, .. } => {
-                    self.on_event(Some(event::Event::MouseMove {
+                    self.post_event(event::Event::MouseMove(event::MouseMove {
                         x: position.x,
                         y: position.y,
                     }));
                 }
-                // glutin::event::WindowEvent::ModifiersChanged(modifiers) => {
-                //
-                // }
+                glutin::event::WindowEvent::CursorEntered { .. } => {
+                    self.post_event(event::MouseEnter);
+                }
+                glutin::event::WindowEvent::CursorLeft { .. } => {
+                    self.post_event(event::MouseLeave);
+                }
+                glutin::event::WindowEvent::ModifiersChanged(state) => {
+                    self.modifiers = keycode::Modifiers::convert(state);
+                    self.post_event(event::ModifiersChange {
+                        modifiers: self.modifiers,
+                    });
+                }
+                glutin::event::WindowEvent::ReceivedCharacter(ch) if !ch.is_control() => {
+                    self.post_event(event::TextInput { ch });
+                }
+                glutin::event::WindowEvent::Ime(glutin::event::Ime::Commit(text)) => {
+                    for ch in text.chars() {
+                        self.post_event(event::TextInput { ch });
+                    }
+                }
                 _ => {}
             },
-            _ => (),
+            glutin::event::Event::UserEvent(user_event) => self.post_event(user_event),
+            _ => {}
         }
     }
 
-    pub fn run(mut self, event_loop: glutin::event_loop::EventLoop<()>) {
-        event_loop.run(move |ev, _, control_flow| {
-            let mut target = self.display.draw();
+    /// Dispatches queued events and updates all layers, then draws them and
+    /// their ui.
+    fn draw_frame(&mut self, timestep: timestep::Timestep) {
+        let mut target = match &self.backend {
+            Backend::Window { display, .. } => display.draw(),
+            Backend::Headless {
+                renderer,
+                width,
+                height,
+                ..
+            } => glium::Frame::new(renderer.get_context().clone(), (*width, *height)),
+        };
+
+        // everything that happened since the last frame, before any update
+        self.input.begin_frame();
+        self.dispatch_events();
 
+        self.fixed_timestep
+            .accumulate(Duration::from_secs_f32(timestep.seconds()));
+        while let Some(fixed_timestep) = self.fixed_timestep.next_step() {
             for layer in self.layer_stack.iter_mut() {
-                layer.on_update(&mut target, &self.input);
+                layer.on_fixed_update(&self.input, fixed_timestep);
             }
+        }
 
-            for layer in self.layer_stack.iter_mut() {
-                layer.on_egui_render(
+        let timestep = timestep.with_alpha(self.fixed_timestep.alpha());
+        for layer in self.layer_stack.iter_mut() {
+            layer.on_update(&mut target, &self.input, timestep);
+        }
+
+        match &mut self.backend {
+            Backend::Window {
+                display,
+                egui_glium,
+            } => {
+                // like EguiGlium::run and paint, but with a single ui pass for
+                // all layers and the painter at hand for native textures
+                let raw_input = egui_glium
+                    .egui_winit
+                    .take_egui_input(display.gl_window().window());
+                let egui::FullOutput {
+                    platform_output,
+                    repaint_after,
+                    textures_delta,
+                    shapes,
+                } = run_ui(
+                    &mut self.layer_stack,
                     &self.input,
-                    &self.display,
+                    &egui_glium.egui_ctx,
+                    &mut egui_glium.painter,
+                    raw_input,
+                );
+                egui_glium.egui_winit.handle_platform_output(
+                    display.gl_window().window(),
+                    &egui_glium.egui_ctx,
+                    platform_output,
+                );
+                self.frame_pacer.request_redraw_after(repaint_after);
+                let clipped_primitives = egui_glium.egui_ctx.tessellate(shapes);
+                egui_glium.painter.paint_and_update_textures(
+                    display,
                     &mut target,
-                    &ev,
-                    control_flow,
-                    &mut self.egui_glium,
+                    egui_glium.egui_ctx.pixels_per_point(),
+                    &clipped_primitives,
+                    &textures_delta,
                 );
             }
+            Backend::Headless {
+                width,
+                height,
+                egui_ctx,
+                painter,
+                ..
+            } => {
+                // the egui painter can only paint to a Display, but the ui
+                // still runs, so layers behave the same as with a window
+                let raw_input = egui::RawInput {
+                    screen_rect: Some(egui::Rect::from_min_size(
+                        egui::Pos2::ZERO,
+                        egui::vec2(*width as f32, *height as f32),
+                    )),
+                    pixels_per_point: Some(1.0),
+                    time: Some(timestep.elapsed()),
+                    ..Default::default()
+                };
+                run_ui(
+                    &mut self.layer_stack,
+                    &self.input,
+                    egui_ctx,
+                    painter,
+                    raw_input,
+                );
+            }
+        }
+
+        let context = self.context();
+        self.frame_capture.capture(&context, &target);
 
-            match target.finish() {
-                Ok(_) => {}
-                Err(why) => error!("Drawing error: {}", why),
+        match target.finish() {
+            Ok(_) => {}
+            Err(why) => error!("Drawing error: {}", why),
+        }
+        self.frame_pacer.on_frame(std::time::Instant::now());
+        // layers posted events, which the next frame dispatches
+        if !self.event_queue.is_empty() {
+            self.frame_pacer.request_redraw();
+        }
+    }
+
+    /// Draws `frames` frames of a headless application, each `frame_time`
+    /// after the previous one, so tests don't depend on how fast they run.
+    ///
+    /// # Panics
+    ///
+    /// If the application has a window, which runs with `run` instead.
+    pub fn run_frames(&mut self, frames: u32, frame_time: Duration) {
+        for _ in 0..frames {
+            let elapsed = match &mut self.backend {
+                Backend::Headless { elapsed, .. } => {
+                    *elapsed += frame_time;
+                    *elapsed
+                }
+                Backend::Window { .. } => panic!("run_frames needs a headless application"),
+            };
+            self.draw_frame(timestep::Timestep::new(
+                frame_time.as_secs_f32(),
+                elapsed.as_secs_f64(),
+            ));
+        }
+    }
+
+    /// Reads back the last frame of a headless application, top row first.
+    /// Returns `None` for an application with a window, whose frames are gone
+    /// once they are shown.
+    pub fn read_pixels(&self) -> Option<image::RgbaImage> {
+        match &self.backend {
+            Backend::Headless {
+                renderer,
+                width,
+                height,
+                ..
+            } => {
+                // the default framebuffer keeps its contents, since there are
+                // no buffers to swap
+                let frame = glium::Frame::new(renderer.get_context().clone(), (*width, *height));
+                let image = capture::read_pixels(renderer, &frame);
+                frame.finish().ok();
+                Some(image)
             }
+            Backend::Window { .. } => None,
+        }
+    }
 
-            let next_frame_time =
-                std::time::Instant::now() + std::time::Duration::from_nanos(16_666_667);
+    /// Runs the application until its window is closed.
+    ///
+    /// # Panics
+    ///
+    /// If the application is headless, which runs with `run_frames` instead.
+    pub fn run(mut self, event_loop: glutin::event_loop::EventLoop<event::UserEvent>) {
+        assert!(
+            matches!(self.backend, Backend::Window { .. }),
+            "run needs an application with a window"
+        );
+        event_loop.run(move |ev, _, control_flow| {
+            let mut egui_consumed = false;
+            if let Backend::Window {
+                display,
+                egui_glium,
+            } = &mut self.backend
+            {
+                match &ev {
+                    glutin::event::Event::WindowEvent { event, .. } => {
+                        // any window event may change what is drawn, whether
+                        // egui wants it or not, so reactive pacing always redraws
+                        egui_consumed = egui_glium.on_event(event).consumed;
+                        self.frame_pacer.request_redraw();
+                    }
+                    glutin::event::Event::UserEvent(_) => self.frame_pacer.request_redraw(),
+                    glutin::event::Event::MainEventsCleared
+                        if !self.minimized
+                            && self.frame_pacer.should_draw(std::time::Instant::now()) =>
+                    {
+                        display.gl_window().window().request_redraw();
+                    }
+                    glutin::event::Event::RedrawRequested(_) if !self.minimized => {
+                        let timestep = self.clock.tick();
+                        self.draw_frame(timestep);
+                    }
+                    _ => {}
+                }
+            }
 
-            *control_flow = glutin::event_loop::ControlFlow::WaitUntil(next_frame_time);
+            self.handle_event(ev, egui_consumed, control_flow);
 
-            self.handle_event(ev, control_flow);
+            match control_flow {
+                glutin::event_loop::ControlFlow::ExitWithCode(_) => {}
+                // no frames to pace, just wait for the window to come back
+                _ if self.minimized => *control_flow = glutin::event_loop::ControlFlow::Wait,
+                _ => *control_flow = self.frame_pacer.control_flow(),
+            }
         });
     }
 }
+
+/// Builds the ui of all layers in one egui pass.
+fn run_ui(
+    layer_stack: &mut layer::LayerStack,
+    input: &input::Input,
+    egui_ctx: &egui::Context,
+    painter: &mut egui_glium::Painter,
+    raw_input: egui::RawInput,
+) -> egui::FullOutput {
+    egui_ctx.run(raw_input, |egui_ctx| {
+        for layer in layer_stack.iter_mut() {
+            layer.on_egui_render(egui_ctx, painter, input);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use glium::Surface;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Record {
+        events: u32,
+        updates: u32,
+        fixed_updates: u32,
+        elapsed: f64,
+    }
+
+    /// Clears to red while space is held, and green otherwise.
+    struct TestLayer(Rc<RefCell<Record>>);
+
+    impl layer::Layer for TestLayer {
+        fn on_event(&mut self, _event: &event::Event) -> bool {
+            self.0.borrow_mut().events += 1;
+            false
+        }
+
+        fn on_fixed_update(&mut self, _input: &input::Input, _timestep: timestep::Timestep) {
+            self.0.borrow_mut().fixed_updates += 1;
+        }
+
+        fn on_update(
+            &mut self,
+            target: &mut glium::Frame,
+            input: &input::Input,
+            timestep: timestep::Timestep,
+        ) {
+            let mut record = self.0.borrow_mut();
+            record.updates += 1;
+            record.elapsed = timestep.elapsed();
+            if input.keys_pressed.contains(&keycode::KeyCode::Space) {
+                target.clear_color(1.0, 0.0, 0.0, 1.0);
+            } else {
+                target.clear_color(0.0, 1.0, 0.0, 1.0);
+            }
+        }
+    }
+
+    #[allow(deprecated)]
+    fn keyboard_input(
+        scancode: glutin::event::ScanCode,
+        state: glutin::event::ElementState,
+        virtual_keycode: Option<glutin::event::VirtualKeyCode>,
+    ) -> glutin::event::KeyboardInput {
+        glutin::event::KeyboardInput {
+            scancode,
+            state,
+            virtual_keycode,
+            modifiers: Default::default(),
+        }
+    }
+
+    #[test]
+    fn keys_release_as_they_were_pressed() {
+        use glutin::event::{ElementState::*, VirtualKeyCode};
+        use keycode::KeyCode;
+
+        let mut keys = PressedKeys::default();
+        let press = keys.on_input(
+            &keyboard_input(16, Pressed, Some(VirtualKeyCode::Q)),
+            keycode::Modifiers::SHIFT,
+        );
+        assert!(matches!(
+            press,
+            event::Event::KeyPress(event::KeyPress {
+                key: KeyCode::Q,
+                modifiers: keycode::Modifiers::SHIFT,
+                ..
+            })
+        ));
+        // the layout switched to AZERTY while Q was held
+        let release = keys.on_input(
+            &keyboard_input(16, Released, Some(VirtualKeyCode::A)),
+            keycode::Modifiers::NONE,
+        );
+        assert_eq!(
+            release,
+            event::Event::KeyRelease(event::KeyRelease {
+                key: KeyCode::Q,
+                physical_key: physical_key::PhysicalKey::from_scancode(16),
+            })
+        );
+
+        // keys without a name go by scancode
+        let press = keys.on_input(
+            &keyboard_input(200, Pressed, None),
+            keycode::Modifiers::NONE,
+        );
+        assert!(matches!(
+            press,
+            event::Event::KeyPress(event::KeyPress {
+                key: KeyCode::Unknown(200),
+                ..
+            })
+        ));
+        let release = keys.on_input(
+            &keyboard_input(200, Released, None),
+            keycode::Modifiers::NONE,
+        );
+        assert!(matches!(
+            release,
+            event::Event::KeyRelease(event::KeyRelease {
+                key: KeyCode::Unknown(200),
+                ..
+            })
+        ));
+        assert!(keys.0.is_empty());
+    }
+
+    #[test]
+    fn egui_keeps_only_what_is_typed_into_it() {
+        use glutin::event::{ElementState::*, WindowEvent};
+
+        // safe, the id is only compared
+        let device_id = unsafe { glutin::event::DeviceId::dummy() };
+        let key = |state| WindowEvent::KeyboardInput {
+            device_id,
+            input: keyboard_input(30, state, Some(glutin::event::VirtualKeyCode::A)),
+            is_synthetic: false,
+        };
+        assert!(egui_keeps(&key(Pressed), true));
+        assert!(!egui_keeps(&key(Pressed), false));
+        assert!(!egui_keeps(&key(Released), true));
+        assert!(egui_keeps(&WindowEvent::ReceivedCharacter('a'), true));
+        assert!(!egui_keeps(&WindowEvent::ReceivedCharacter('a'), false));
+
+        let click = WindowEvent::MouseInput {
+            device_id,
+            state: Pressed,
+            button: glutin::event::MouseButton::Left,
+            modifiers: Default::default(),
+        };
+        assert!(!egui_keeps(&click, true));
+    }
+
+    // needs OSMesa, run with `cargo test -- --ignored`
+    #[test]
+    #[ignore = "needs OSMesa"]
+    fn headless_application_drives_layers() {
+        let mut app = Application::new_headless(64, 32)
+            .expect("headless tests need a headless OpenGL context");
+        let record = Rc::new(RefCell::new(Record::default()));
+        app.push_layer(Box::new(TestLayer(record.clone())));
+
+        app.run_frames(3, Duration::from_millis(20));
+        let image = app.read_pixels().unwrap();
+        assert_eq!(image.dimensions(), (64, 32));
+        assert_eq!(image.get_pixel(10, 10).0, [0, 255, 0, 255]);
+
+        app.post_event(event::KeyPress::new(
+            keycode::KeyCode::Space,
+            physical_key::PhysicalKey::Space,
+        ));
+        app.run_frames(1, Duration::from_millis(20));
+        assert_eq!(
+            app.read_pixels().unwrap().get_pixel(63, 31).0,
+            [255, 0, 0, 255]
+        );
+
+        let record = record.borrow();
+        assert_eq!(record.events, 1);
+        assert_eq!(record.updates, 4);
+        // 80 ms at 60 Hz
+        assert_eq!(record.fixed_updates, 4);
+        assert!((record.elapsed - 0.08).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,232 @@
+use std::{borrow::Cow, fmt};
+
+use glium::{
+    backend::Facade,
+    vertex::{AttributeType, MultiVerticesSource, VertexBufferAny, VerticesSource},
+};
+
+/// The type of a vertex attribute, as declared in the shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderDataType {
+    Float,
+    Float2,
+    Float3,
+    Float4,
+    Mat3,
+    Mat4,
+    Int,
+    Int2,
+    Int3,
+    Int4,
+}
+
+impl ShaderDataType {
+    /// Size in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            ShaderDataType::Float | ShaderDataType::Int => 4,
+            ShaderDataType::Float2 | ShaderDataType::Int2 => 4 * 2,
+            ShaderDataType::Float3 | ShaderDataType::Int3 => 4 * 3,
+            ShaderDataType::Float4 | ShaderDataType::Int4 => 4 * 4,
+            ShaderDataType::Mat3 => 4 * 3 * 3,
+            ShaderDataType::Mat4 => 4 * 4 * 4,
+        }
+    }
+
+    fn attribute_type(&self) -> AttributeType {
+        match self {
+            ShaderDataType::Float => AttributeType::F32,
+            ShaderDataType::Float2 => AttributeType::F32F32,
+            ShaderDataType::Float3 => AttributeType::F32F32F32,
+            ShaderDataType::Float4 => AttributeType::F32F32F32F32,
+            ShaderDataType::Mat3 => AttributeType::F32x3x3,
+            ShaderDataType::Mat4 => AttributeType::F32x4x4,
+            ShaderDataType::Int => AttributeType::I32,
+            ShaderDataType::Int2 => AttributeType::I32I32,
+            ShaderDataType::Int3 => AttributeType::I32I32I32,
+            ShaderDataType::Int4 => AttributeType::I32I32I32I32,
+        }
+    }
+}
+
+/// One named attribute of a vertex. The name has to match the `in` variable
+/// of the vertex shader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferElement {
+    pub name: String,
+    pub data_type: ShaderDataType,
+    pub normalized: bool,
+    offset: usize,
+}
+
+impl BufferElement {
+    pub fn new(data_type: ShaderDataType, name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            data_type,
+            normalized: false,
+            offset: 0,
+        }
+    }
+
+    /// Marks an integer attribute to be converted to a float in `0.0..=1.0`.
+    pub fn normalized(mut self) -> Self {
+        self.normalized = true;
+        self
+    }
+
+    /// Offset in bytes from the start of the vertex.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// Describes the attributes of each vertex in a buffer, in the order they are
+/// laid out in memory.
+///
+/// ```ignore
+/// let layout = BufferLayout::new(vec![
+///     BufferElement::new(ShaderDataType::Float3, "position"),
+///     BufferElement::new(ShaderDataType::Float4, "color"),
+/// ]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferLayout {
+    elements: Vec<BufferElement>,
+    stride: usize,
+}
+
+impl BufferLayout {
+    pub fn new(mut elements: Vec<BufferElement>) -> Self {
+        let mut offset = 0;
+        for element in elements.iter_mut() {
+            element.offset = offset;
+            offset += element.data_type.size();
+        }
+        Self {
+            elements,
+            stride: offset,
+        }
+    }
+
+    pub fn elements(&self) -> &[BufferElement] {
+        &self.elements
+    }
+
+    /// Size in bytes of a whole vertex.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    fn vertex_format(&self) -> glium::vertex::VertexFormat {
+        self.elements
+            .iter()
+            .map(|element| {
+                (
+                    Cow::Owned(element.name.clone()),
+                    element.offset,
+                    // bind by name rather than by location
+                    -1,
+                    element.data_type.attribute_type(),
+                    element.normalized,
+                )
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+#[derive(Debug)]
+pub enum BufferError {
+    /// The vertex type is not the size of a vertex in the layout.
+    LayoutMismatch {
+        vertex_size: usize,
+        stride: usize,
+    },
+    Create(glium::vertex::BufferCreationError),
+}
+
+impl fmt::Display for BufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BufferError::LayoutMismatch {
+                vertex_size,
+                stride,
+            } => write!(
+                f,
+                "vertices are {} bytes but the layout describes {} bytes",
+                vertex_size, stride
+            ),
+            BufferError::Create(why) => write!(f, "could not create vertex buffer: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for BufferError {}
+
+impl From<glium::vertex::BufferCreationError> for BufferError {
+    fn from(why: glium::vertex::BufferCreationError) -> Self {
+        BufferError::Create(why)
+    }
+}
+
+/// A vertex buffer whose attributes are described at runtime by a
+/// `BufferLayout`, so any vertex type can be drawn by the same `Renderer`.
+pub struct VertexBuffer {
+    buffer: VertexBufferAny,
+    layout: BufferLayout,
+}
+
+impl VertexBuffer {
+    /// Uploads `vertices`, which must be laid out exactly as `layout`
+    /// describes. `T` is usually a `#[repr(C)]` struct or an array of floats.
+    pub fn new<T: Copy + Send + 'static>(
+        display: &impl Facade,
+        layout: BufferLayout,
+        vertices: &[T],
+    ) -> Result<Self, BufferError> {
+        let vertex_size = std::mem::size_of::<T>();
+        if vertex_size != layout.stride() {
+            return Err(BufferError::LayoutMismatch {
+                vertex_size,
+                stride: layout.stride(),
+            });
+        }
+        // Safety: the check above makes sure every attribute is inside a vertex
+        let buffer = unsafe {
+            glium::VertexBuffer::new_raw(display, vertices, layout.vertex_format(), layout.stride())
+        }?;
+        Ok(Self {
+            buffer: buffer.into(),
+            layout,
+        })
+    }
+
+    pub fn layout(&self) -> &BufferLayout {
+        &self.layout
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.len() == 0
+    }
+}
+
+/// Vertex buffers that each provide some attributes of the same vertices.
+pub struct VertexBuffers<'a>(pub &'a [VertexBuffer]);
+
+impl<'a> MultiVerticesSource<'a> for VertexBuffers<'a> {
+    type Iterator = std::iter::Map<
+        std::slice::Iter<'a, VertexBuffer>,
+        fn(&'a VertexBuffer) -> VerticesSource<'a>,
+    >;
+
+    fn iter(self) -> Self::Iterator {
+        self.0
+            .iter()
+            .map(|vertex_buffer| (&vertex_buffer.buffer).into())
+    }
+}
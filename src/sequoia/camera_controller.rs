@@ -0,0 +1,312 @@
+use super::{
+    event::{Event, EventDispatcher, MouseScroll},
+    input::Input,
+    mousecode::MouseCode,
+    physical_key::PhysicalKey,
+    renderer::{OrthographicCamera, PerspectiveCamera},
+    timestep::Timestep,
+};
+
+/// Owns an `OrthographicCamera` and moves it around: WASD to pan, Q and E to
+/// rotate (if enabled) and the mouse wheel to zoom. Keys go by position, so
+/// on an AZERTY keyboard it's ZQSD, A and E.
+///
+/// The visible area is always `zoom_level` units from the center to the top
+/// and bottom edge, with the sides following the aspect ratio.
+pub struct OrthographicCameraController {
+    aspect_ratio: f32,
+    zoom_level: f32,
+    camera: OrthographicCamera,
+    rotation: bool,
+    camera_position: glam::Vec3,
+    camera_rotation: f32,
+    // units per second
+    translation_speed: f32,
+    // radians per second
+    rotation_speed: f32,
+}
+
+impl OrthographicCameraController {
+    pub fn new(aspect_ratio: f32, rotation: bool) -> Self {
+        let zoom_level = 1.0;
+        let camera = OrthographicCamera::new(
+            -aspect_ratio * zoom_level,
+            aspect_ratio * zoom_level,
+            -zoom_level,
+            zoom_level,
+            glam::Vec3::ZERO,
+            0.0,
+        );
+        Self {
+            aspect_ratio,
+            zoom_level,
+            camera,
+            rotation,
+            camera_position: glam::Vec3::ZERO,
+            camera_rotation: 0.0,
+            translation_speed: zoom_level,
+            rotation_speed: 90.0_f32.to_radians(),
+        }
+    }
+
+    pub fn on_update(&mut self, input: &Input, timestep: Timestep) {
+        let translation = self.translation_speed * timestep.seconds();
+        let rotation = self.rotation_speed * timestep.seconds();
+
+        // move relative to the camera, so up is always up on screen
+        let (sin, cos) = self.camera_rotation.sin_cos();
+        let right = glam::vec3(cos, sin, 0.0);
+        let up = glam::vec3(-sin, cos, 0.0);
+
+        if input.physical_keys_pressed.contains(&PhysicalKey::A) {
+            self.camera_position -= right * translation;
+        }
+        if input.physical_keys_pressed.contains(&PhysicalKey::D) {
+            self.camera_position += right * translation;
+        }
+        if input.physical_keys_pressed.contains(&PhysicalKey::W) {
+            self.camera_position += up * translation;
+        }
+        if input.physical_keys_pressed.contains(&PhysicalKey::S) {
+            self.camera_position -= up * translation;
+        }
+        self.camera.set_position(self.camera_position);
+
+        if self.rotation {
+            if input.physical_keys_pressed.contains(&PhysicalKey::Q) {
+                self.camera_rotation += rotation;
+            }
+            if input.physical_keys_pressed.contains(&PhysicalKey::E) {
+                self.camera_rotation -= rotation;
+            }
+            self.camera_rotation %= std::f32::consts::TAU;
+            self.camera.set_rotation(self.camera_rotation);
+        }
+    }
+
+    /// Zooms on `MouseScroll`, which it handles.
+    pub fn on_event(&mut self, event: &Event) -> bool {
+        let mut dispatcher = EventDispatcher::new(event);
+        dispatcher.dispatch::<MouseScroll>(|e| {
+            self.set_zoom_level(self.zoom_level - e.y as f32 * 0.25);
+            true
+        });
+        dispatcher.handled()
+    }
+
+    /// Recomputes the projection for a new viewport size, in pixels. The
+    /// camera may draw into a part of the window, so window resizes are not
+    /// followed on their own: call this with the size of whatever it draws
+    /// into.
+    pub fn on_resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.aspect_ratio = width as f32 / height as f32;
+        self.update_projection();
+    }
+
+    pub fn zoom_level(&self) -> f32 {
+        self.zoom_level
+    }
+
+    pub fn set_zoom_level(&mut self, zoom_level: f32) {
+        self.zoom_level = zoom_level.max(0.25);
+        // keep panning at the same speed on screen
+        self.translation_speed = self.zoom_level;
+        self.update_projection();
+    }
+
+    fn update_projection(&mut self) {
+        self.camera.set_projection(
+            -self.aspect_ratio * self.zoom_level,
+            self.aspect_ratio * self.zoom_level,
+            -self.zoom_level,
+            self.zoom_level,
+        );
+    }
+
+    pub fn camera(&self) -> &OrthographicCamera {
+        &self.camera
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorCameraMode {
+    /// Hold the right mouse button to look around, and move with WASD, Q
+    /// (down) and E (up). The mouse wheel moves forward and back.
+    Fly,
+    /// Hold the right mouse button to orbit around the focal point and the
+    /// middle mouse button to pan it. The mouse wheel zooms.
+    Orbit,
+}
+
+/// Owns a `PerspectiveCamera` and moves it like the camera of an editor.
+pub struct EditorCameraController {
+    camera: PerspectiveCamera,
+    mode: EditorCameraMode,
+    focal_point: glam::Vec3,
+    distance: f32,
+    // units per second
+    move_speed: f32,
+    // radians per pixel the mouse moves
+    look_speed: f32,
+}
+
+impl EditorCameraController {
+    pub fn new(aspect_ratio: f32, mode: EditorCameraMode) -> Self {
+        let camera = PerspectiveCamera::new(45.0_f32.to_radians(), aspect_ratio, 0.1, 1000.0);
+        let mut controller = Self {
+            camera,
+            mode,
+            focal_point: glam::Vec3::ZERO,
+            distance: 5.0,
+            move_speed: 3.0,
+            look_speed: 0.005,
+        };
+        controller.camera.set_rotation(0.0, -20.0_f32.to_radians());
+        controller.camera.set_position(
+            controller.focal_point - controller.camera.forward() * controller.distance,
+        );
+        controller
+    }
+
+    pub fn on_update(&mut self, input: &Input, timestep: Timestep) {
+        let looking = input.is_mouse_button_down(MouseCode::Button1);
+        if let Some(delta) = Self::drag_delta(input, MouseCode::Button1) {
+            self.camera.set_rotation(
+                self.camera.yaw() + delta.x * self.look_speed,
+                self.camera.pitch() - delta.y * self.look_speed,
+            );
+        }
+
+        match self.mode {
+            EditorCameraMode::Fly => {
+                if looking {
+                    let mut direction = glam::Vec3::ZERO;
+                    let keys = &input.physical_keys_pressed;
+                    if keys.contains(&PhysicalKey::W) {
+                        direction += self.camera.forward();
+                    }
+                    if keys.contains(&PhysicalKey::S) {
+                        direction -= self.camera.forward();
+                    }
+                    if keys.contains(&PhysicalKey::D) {
+                        direction += self.camera.right();
+                    }
+                    if keys.contains(&PhysicalKey::A) {
+                        direction -= self.camera.right();
+                    }
+                    if keys.contains(&PhysicalKey::E) {
+                        direction += glam::Vec3::Y;
+                    }
+                    if keys.contains(&PhysicalKey::Q) {
+                        direction -= glam::Vec3::Y;
+                    }
+                    self.camera.set_position(
+                        self.camera.position()
+                            + direction.normalize_or_zero() * self.move_speed * timestep.seconds(),
+                    );
+                }
+            }
+            EditorCameraMode::Orbit => {
+                if let Some(delta) = Self::drag_delta(input, MouseCode::Button2) {
+                    // pan further when further away, so it follows the mouse
+                    let pan_speed = self.distance * 0.002;
+                    self.focal_point +=
+                        (-self.camera.right() * delta.x + self.camera.up() * delta.y) * pan_speed;
+                }
+                self.camera
+                    .set_position(self.focal_point - self.camera.forward() * self.distance);
+            }
+        }
+    }
+
+    /// How far the mouse moved this frame while `button` was held. The frame
+    /// the button goes down only starts the drag, so the cursor moving to
+    /// where it was pressed doesn't turn or pan the camera.
+    fn drag_delta(input: &Input, button: MouseCode) -> Option<glam::Vec2> {
+        if !input.is_mouse_button_down(button) || input.was_mouse_button_pressed_this_frame(button)
+        {
+            return None;
+        }
+        let (x, y) = input.mouse_delta();
+        Some(glam::vec2(x as f32, y as f32))
+    }
+
+    /// Moves or zooms on `MouseScroll`, which it handles.
+    pub fn on_event(&mut self, event: &Event) -> bool {
+        let mut dispatcher = EventDispatcher::new(event);
+        dispatcher.dispatch::<MouseScroll>(|e| {
+            let y = e.y as f32;
+            match self.mode {
+                EditorCameraMode::Fly => self
+                    .camera
+                    .set_position(self.camera.position() + self.camera.forward() * y * 0.5),
+                EditorCameraMode::Orbit => {
+                    self.distance = (self.distance - y * 0.5).max(0.5);
+                }
+            }
+            true
+        });
+        dispatcher.handled()
+    }
+
+    /// Updates the aspect ratio for a new viewport size, in pixels. Like
+    /// `OrthographicCameraController::on_resize`, this is not called for
+    /// window resizes.
+    pub fn on_resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.camera.set_aspect_ratio(width as f32 / height as f32);
+    }
+
+    pub fn mode(&self) -> EditorCameraMode {
+        self.mode
+    }
+
+    /// Switches mode without moving the camera. Orbiting continues around
+    /// the point `distance` in front of the camera.
+    pub fn set_mode(&mut self, mode: EditorCameraMode) {
+        if mode == EditorCameraMode::Orbit && self.mode == EditorCameraMode::Fly {
+            self.focal_point = self.camera.position() + self.camera.forward() * self.distance;
+        }
+        self.mode = mode;
+    }
+
+    pub fn camera(&self) -> &PerspectiveCamera {
+        &self.camera
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequoia::event;
+
+    #[test]
+    fn editor_camera_ignores_the_mouse_before_the_look_button() {
+        let mut controller = EditorCameraController::new(1.0, EditorCameraMode::Fly);
+        let yaw = controller.camera().yaw();
+        let timestep = Timestep::new(0.016, 0.0);
+
+        // the cursor comes from far away, then the look button goes down
+        let mut input = Input::new();
+        input.on_event(&event::MouseMove { x: 10.0, y: 10.0 }.into());
+        input.on_event(&event::MouseMove { x: 600.0, y: 400.0 }.into());
+        input.on_event(
+            &event::MouseButtonPress {
+                mouse_button: MouseCode::Button1,
+            }
+            .into(),
+        );
+        controller.on_update(&input, timestep);
+        assert_eq!(controller.camera().yaw(), yaw);
+
+        input.begin_frame();
+        input.on_event(&event::MouseMove { x: 610.0, y: 400.0 }.into());
+        controller.on_update(&input, timestep);
+        assert!((controller.camera().yaw() - (yaw + 10.0 * 0.005)).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use glium::{backend::Facade, uniforms::MagnifySamplerFilter, BlitTarget, Surface};
+
+/// Reads the color of `surface` back from the GPU into an RGBA image, top row
+/// first. This stalls until everything drawn to `surface` so far is finished.
+pub fn read_pixels<S: Surface>(display: &impl Facade, surface: &S) -> image::RgbaImage {
+    let (width, height) = surface.get_dimensions();
+
+    // blit into a plain RGBA texture first, since the surface may be a frame
+    // or use a different format
+    let texture = glium::texture::Texture2d::empty_with_format(
+        display,
+        glium::texture::UncompressedFloatFormat::U8U8U8U8,
+        glium::texture::MipmapsOption::NoMipmap,
+        width,
+        height,
+    )
+    .unwrap();
+    surface.blit_whole_color_to(
+        &texture.as_surface(),
+        &BlitTarget {
+            left: 0,
+            bottom: 0,
+            width: width as i32,
+            height: height as i32,
+        },
+        MagnifySamplerFilter::Nearest,
+    );
+
+    into_image(texture.read())
+}
+
+/// Turns pixels read from a texture into an image, top row first.
+pub fn into_image(raw: glium::texture::RawImage2d<u8>) -> image::RgbaImage {
+    let mut image = image::RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned())
+        .expect("texture data should be RGBA8");
+    // OpenGL stores the bottom row first
+    image::imageops::flip_vertical_in_place(&mut image);
+    image
+}
+
+/// Saves `image` as a PNG on a background thread, so drawing doesn't wait for
+/// the encoder. Errors are logged.
+pub fn save_png(image: image::RgbaImage, path: PathBuf) {
+    std::thread::spawn(move || {
+        if let Some(dir) = path.parent() {
+            if let Err(why) = std::fs::create_dir_all(dir) {
+                error!("Could not create {}: {}", dir.display(), why);
+                return;
+            }
+        }
+        match image.save(&path) {
+            Ok(_) => info!("Saved {}", path.display()),
+            Err(why) => error!("Could not save {}: {}", path.display(), why),
+        }
+    });
+}
+
+/// The current UTC time as `2023-11-14_22-13-20_123`, for file names that
+/// sort by time.
+pub fn timestamp() -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    format_timestamp(since_epoch)
+}
+
+fn format_timestamp(since_epoch: Duration) -> String {
+    let seconds = since_epoch.as_secs();
+    let (days, seconds_of_day) = (seconds / 86_400, seconds % 86_400);
+
+    // days since 1970-01-01 to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}_{:03}",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Saves frames drawn by the application as PNGs: single screenshots, or a
+/// numbered sequence of the next few frames.
+#[derive(Debug)]
+pub struct FrameCapture {
+    directory: PathBuf,
+    screenshot_requested: bool,
+    recording: Option<Recording>,
+}
+
+#[derive(Debug)]
+struct Recording {
+    directory: PathBuf,
+    frame: u32,
+    remaining: u32,
+}
+
+impl FrameCapture {
+    /// Captures are saved in `directory`, which is created when needed.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            screenshot_requested: false,
+            recording: None,
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn set_directory(&mut self, directory: impl Into<PathBuf>) {
+        self.directory = directory.into();
+    }
+
+    /// Saves the next frame as `screenshot_<timestamp>.png`.
+    pub fn request_screenshot(&mut self) {
+        self.screenshot_requested = true;
+    }
+
+    /// Saves the next `frames` frames as `recording_<timestamp>/00000.png`,
+    /// `00001.png` and so on. Replaces a recording that is still running.
+    pub fn record(&mut self, frames: u32) {
+        if frames == 0 {
+            self.recording = None;
+            return;
+        }
+        let directory = self.directory.join(format!("recording_{}", timestamp()));
+        info!("Recording {} frames to {}", frames, directory.display());
+        self.recording = Some(Recording {
+            directory,
+            frame: 0,
+            remaining: frames,
+        });
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Whether the frame being drawn has to be captured.
+    pub fn is_pending(&self) -> bool {
+        self.screenshot_requested || self.recording.is_some()
+    }
+
+    /// Saves `surface` if a capture is pending. Call once per frame, after
+    /// everything is drawn.
+    pub fn capture<S: Surface>(&mut self, display: &impl Facade, surface: &S) {
+        if !self.is_pending() {
+            return;
+        }
+        let image = read_pixels(display, surface);
+
+        if self.screenshot_requested {
+            self.screenshot_requested = false;
+            let path = self
+                .directory
+                .join(format!("screenshot_{}.png", timestamp()));
+            save_png(image.clone(), path);
+        }
+
+        if let Some(recording) = &mut self.recording {
+            let path = recording
+                .directory
+                .join(format!("{:05}.png", recording.frame));
+            save_png(image, path);
+            recording.frame += 1;
+            recording.remaining -= 1;
+            if recording.remaining == 0 {
+                self.recording = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_utc_dates() {
+        assert_eq!(format_timestamp(Duration::ZERO), "1970-01-01_00-00-00_000");
+        assert_eq!(
+            format_timestamp(Duration::from_millis(1_700_000_000_042)),
+            "2023-11-14_22-13-20_042"
+        );
+        // leap day
+        assert_eq!(
+            format_timestamp(Duration::from_secs(951_782_400)),
+            "2000-02-29_00-00-00_000"
+        );
+    }
+}
@@ -1,13 +1,553 @@
-use super::{keycode::KeyCode, mousecode::MouseCode};
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::VecDeque,
+    fmt,
+    ops::BitOr,
+    path::PathBuf,
+    rc::Rc,
+    sync::{mpsc, Arc},
+};
 
-#[derive(Debug, Clone, Copy)]
+use glium::glutin::event_loop::{EventLoopClosed, EventLoopProxy};
+
+use super::{
+    keycode::{KeyCode, Modifiers},
+    mousecode::MouseCode,
+    physical_key::PhysicalKey,
+};
+
+/// Groups of events, for handling whole kinds of events at once. An event can
+/// be in several categories, like a key press is both `INPUT` and `KEYBOARD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventCategory(u8);
+
+impl EventCategory {
+    pub const NONE: EventCategory = EventCategory(0);
+    pub const APPLICATION: EventCategory = EventCategory(1 << 0);
+    pub const WINDOW: EventCategory = EventCategory(1 << 1);
+    pub const INPUT: EventCategory = EventCategory(1 << 2);
+    pub const KEYBOARD: EventCategory = EventCategory(1 << 3);
+    pub const MOUSE: EventCategory = EventCategory(1 << 4);
+
+    pub const fn union(self, other: EventCategory) -> EventCategory {
+        EventCategory(self.0 | other.0)
+    }
+
+    /// Whether all categories in `other` are in `self`.
+    pub fn contains(self, other: EventCategory) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether any category in `other` is in `self`.
+    pub fn intersects(self, other: EventCategory) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for EventCategory {
+    type Output = EventCategory;
+
+    fn bitor(self, rhs: EventCategory) -> EventCategory {
+        self.union(rhs)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowClose;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowResize {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowFocus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowUnfocus;
+
+/// The application doesn't draw while the window is minimized. Only sent on
+/// Windows, where minimizing resizes the window to nothing; other platforms
+/// keep drawing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowMinimize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowRestore;
+
+/// The window moved to `x`, `y` on the desktop, in physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowMove {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// The window moved to a monitor with a different DPI, or the DPI setting
+/// changed. A `WindowResize` to the new size follows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowRescale {
+    pub scale_factor: f64,
+}
+
+/// A file was dropped on the window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDrop {
+    pub path: PathBuf,
+}
+
+/// A file is being dragged over the window. Either a `FileDrop` or a
+/// `FileHoverCancel` follows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileHover {
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileHoverCancel;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    /// The key in the current keyboard layout, for showing its label.
+    pub key: KeyCode,
+    /// Where the key is on the keyboard, for bindings that shouldn't move
+    /// with the layout.
+    pub physical_key: PhysicalKey,
+    /// The modifier keys held down at the time, for shortcuts like Ctrl+S.
+    pub modifiers: Modifiers,
+    /// Whether this press comes from holding the key down. The application
+    /// fills this in when it dispatches the event.
+    pub repeat: bool,
+}
+
+impl KeyPress {
+    /// A first press, without modifiers.
+    pub fn new(key: KeyCode, physical_key: PhysicalKey) -> Self {
+        Self {
+            key,
+            physical_key,
+            modifiers: Modifiers::NONE,
+            repeat: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRelease {
+    pub key: KeyCode,
+    pub physical_key: PhysicalKey,
+}
+
+/// The modifier keys held down changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModifiersChange {
+    pub modifiers: Modifiers,
+}
+
+/// A character typed, for text fields. Unlike `KeyPress`, this follows the
+/// keyboard layout and input methods, like dead keys or an IME composing
+/// Chinese characters. Control characters like backspace are left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextInput {
+    pub ch: char,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseButtonPress {
+    pub mouse_button: MouseCode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseButtonRelease {
+    pub mouse_button: MouseCode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MouseScroll {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MouseMove {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// The cursor moved onto the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEnter;
+
+/// The cursor moved off the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseLeave;
+
+/// An event defined by the game, like "level loaded", carrying any payload.
+/// Post one with `EventQueue::post` or, from other threads, `EventProxy::send`,
+/// and handle it with `EventDispatcher::dispatch_user`.
+#[derive(Clone)]
+pub struct UserEvent {
+    type_name: &'static str,
+    payload: Arc<dyn Any + Send + Sync>,
+}
+
+impl UserEvent {
+    pub fn new<T: Any + Send + Sync>(payload: T) -> Self {
+        Self {
+            type_name: std::any::type_name::<T>(),
+            payload: Arc::new(payload),
+        }
+    }
+
+    /// Whether the payload is a `T`.
+    pub fn is<T: Any>(&self) -> bool {
+        self.payload.is::<T>()
+    }
+
+    /// The payload, if it is a `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.payload.downcast_ref()
+    }
+}
+
+impl fmt::Debug for UserEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("UserEvent").field(&self.type_name).finish()
+    }
+}
+
+/// Clones of an event are equal, other events are not, even with equal
+/// payloads.
+impl PartialEq for UserEvent {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.payload, &other.payload)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Event {
-    WindowClose,
-    WindowResize { width: u32, height: u32 },
-    KeyPress { key: KeyCode },
-    KeyRelease { key: KeyCode },
-    MouseButtonPress { mouse_button: MouseCode },
-    MouseButtonRelease { mouse_button: MouseCode },
-    MouseScroll { x: f64, y: f64 },
-    MouseMove { x: f64, y: f64 }
+    WindowClose(WindowClose),
+    WindowResize(WindowResize),
+    WindowFocus(WindowFocus),
+    WindowUnfocus(WindowUnfocus),
+    WindowMinimize(WindowMinimize),
+    WindowRestore(WindowRestore),
+    WindowMove(WindowMove),
+    WindowRescale(WindowRescale),
+    FileDrop(FileDrop),
+    FileHover(FileHover),
+    FileHoverCancel(FileHoverCancel),
+    KeyPress(KeyPress),
+    KeyRelease(KeyRelease),
+    ModifiersChange(ModifiersChange),
+    TextInput(TextInput),
+    MouseButtonPress(MouseButtonPress),
+    MouseButtonRelease(MouseButtonRelease),
+    MouseScroll(MouseScroll),
+    MouseMove(MouseMove),
+    MouseEnter(MouseEnter),
+    MouseLeave(MouseLeave),
+    UserEvent(UserEvent),
+}
+
+/// An event type that `EventDispatcher::dispatch` can route to a handler.
+pub trait EventType {
+    const CATEGORY: EventCategory;
+
+    /// The event as this type, if it is one.
+    fn from_event(event: &Event) -> Option<&Self>;
+}
+
+macro_rules! event_types {
+    ($($name:ident: $category:expr),* $(,)?) => {
+        $(
+            impl EventType for $name {
+                const CATEGORY: EventCategory = $category;
+
+                fn from_event(event: &Event) -> Option<&Self> {
+                    match event {
+                        Event::$name(e) => Some(e),
+                        _ => None,
+                    }
+                }
+            }
+
+            impl From<$name> for Event {
+                fn from(e: $name) -> Self {
+                    Event::$name(e)
+                }
+            }
+        )*
+
+        impl Event {
+            pub fn category(&self) -> EventCategory {
+                match self {
+                    $(Event::$name(_) => $name::CATEGORY,)*
+                }
+            }
+        }
+    };
+}
+
+event_types! {
+    WindowClose: EventCategory::WINDOW,
+    WindowResize: EventCategory::WINDOW,
+    WindowFocus: EventCategory::WINDOW,
+    WindowUnfocus: EventCategory::WINDOW,
+    WindowMinimize: EventCategory::WINDOW,
+    WindowRestore: EventCategory::WINDOW,
+    WindowMove: EventCategory::WINDOW,
+    WindowRescale: EventCategory::WINDOW,
+    FileDrop: EventCategory::WINDOW,
+    FileHover: EventCategory::WINDOW,
+    FileHoverCancel: EventCategory::WINDOW,
+    KeyPress: EventCategory::INPUT.union(EventCategory::KEYBOARD),
+    KeyRelease: EventCategory::INPUT.union(EventCategory::KEYBOARD),
+    ModifiersChange: EventCategory::INPUT.union(EventCategory::KEYBOARD),
+    TextInput: EventCategory::INPUT.union(EventCategory::KEYBOARD),
+    MouseButtonPress: EventCategory::INPUT.union(EventCategory::MOUSE),
+    MouseButtonRelease: EventCategory::INPUT.union(EventCategory::MOUSE),
+    MouseScroll: EventCategory::INPUT.union(EventCategory::MOUSE),
+    MouseMove: EventCategory::INPUT.union(EventCategory::MOUSE),
+    MouseEnter: EventCategory::INPUT.union(EventCategory::MOUSE),
+    MouseLeave: EventCategory::INPUT.union(EventCategory::MOUSE),
+    UserEvent: EventCategory::APPLICATION,
+}
+
+impl Event {
+    pub fn is_in_category(&self, category: EventCategory) -> bool {
+        self.category().intersects(category)
+    }
+}
+
+/// Routes one event to handlers for specific event types. Each handler
+/// returns whether it handled the event, which stops it from reaching the
+/// layers below.
+///
+/// ```ignore
+/// fn on_event(&mut self, event: &Event) -> bool {
+///     let mut dispatcher = EventDispatcher::new(event);
+///     dispatcher.dispatch::<MouseScroll>(|e| self.zoom(e.y));
+///     dispatcher.dispatch::<WindowResize>(|e| {
+///         self.resize(e.width, e.height);
+///         false
+///     });
+///     dispatcher.handled()
+/// }
+/// ```
+pub struct EventDispatcher<'a> {
+    event: &'a Event,
+    handled: bool,
+}
+
+impl<'a> EventDispatcher<'a> {
+    pub fn new(event: &'a Event) -> Self {
+        Self {
+            event,
+            handled: false,
+        }
+    }
+
+    /// Calls `handler` if the event is a `T`, and returns whether it was.
+    pub fn dispatch<T: EventType>(&mut self, handler: impl FnOnce(&T) -> bool) -> bool {
+        match T::from_event(self.event) {
+            Some(e) => {
+                self.handled |= handler(e);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Calls `handler` if the event is a `UserEvent` with a `T` payload, and
+    /// returns whether it was.
+    pub fn dispatch_user<T: Any>(&mut self, handler: impl FnOnce(&T) -> bool) -> bool {
+        match UserEvent::from_event(self.event).and_then(UserEvent::downcast_ref) {
+            Some(payload) => {
+                self.handled |= handler(payload);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Calls `handler` if the event is in any of `category`, and returns
+    /// whether it was.
+    pub fn dispatch_category(
+        &mut self,
+        category: EventCategory,
+        handler: impl FnOnce(&Event) -> bool,
+    ) -> bool {
+        if self.event.is_in_category(category) {
+            self.handled |= handler(self.event);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether any handler handled the event.
+    pub fn handled(&self) -> bool {
+        self.handled
+    }
+}
+
+/// Events waiting to be dispatched. The application collects events here
+/// as they arrive and dispatches them all at the start of the next frame, so
+/// the input state doesn't change halfway through a frame.
+///
+/// Cloning gives another handle to the same queue, so layers can keep one to
+/// post their own events. Events posted while a frame runs are dispatched at
+/// the start of the next one.
+#[derive(Debug, Clone, Default)]
+pub struct EventQueue(Rc<RefCell<VecDeque<Event>>>);
+
+impl EventQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn post(&self, event: impl Into<Event>) {
+        self.0.borrow_mut().push_back(event.into());
+    }
+
+    /// Removes and returns all queued events, oldest first.
+    pub fn take(&self) -> VecDeque<Event> {
+        std::mem::take(&mut *self.0.borrow_mut())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+}
+
+/// Sends user events to the application from any thread. They are
+/// dispatched at the start of the next frame, like events from the window.
+#[derive(Clone)]
+pub struct EventProxy(ProxyKind);
+
+#[derive(Clone)]
+enum ProxyKind {
+    EventLoop(EventLoopProxy<UserEvent>),
+    Channel(mpsc::Sender<UserEvent>),
+}
+
+impl EventProxy {
+    /// Sends `payload` as a `UserEvent`. Fails once the application has
+    /// exited, returning the event.
+    pub fn send<T: Any + Send + Sync>(&self, payload: T) -> Result<(), EventLoopClosed<UserEvent>> {
+        self.send_event(UserEvent::new(payload))
+    }
+
+    pub fn send_event(&self, event: UserEvent) -> Result<(), EventLoopClosed<UserEvent>> {
+        match &self.0 {
+            ProxyKind::EventLoop(proxy) => proxy.send_event(event),
+            ProxyKind::Channel(sender) => sender.send(event).map_err(|e| EventLoopClosed(e.0)),
+        }
+    }
+}
+
+impl fmt::Debug for EventProxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventProxy").finish_non_exhaustive()
+    }
+}
+
+/// Wakes the event loop, which hands the event to the application.
+impl From<EventLoopProxy<UserEvent>> for EventProxy {
+    fn from(proxy: EventLoopProxy<UserEvent>) -> Self {
+        EventProxy(ProxyKind::EventLoop(proxy))
+    }
+}
+
+/// For applications without an event loop, which receive from the other end.
+impl From<mpsc::Sender<UserEvent>> for EventProxy {
+    fn from(sender: mpsc::Sender<UserEvent>) -> Self {
+        EventProxy(ProxyKind::Channel(sender))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dispatch_routes_by_type() {
+        let event: Event = MouseScroll { x: 0.0, y: 2.0 }.into();
+        let mut dispatcher = EventDispatcher::new(&event);
+
+        assert!(!dispatcher.dispatch::<KeyPress>(|_| panic!("not a key press")));
+        assert!(!dispatcher.handled());
+
+        let mut scrolled = 0.0;
+        assert!(dispatcher.dispatch::<MouseScroll>(|e| {
+            scrolled = e.y;
+            true
+        }));
+        assert_eq!(scrolled, 2.0);
+        assert!(dispatcher.handled());
+    }
+
+    #[test]
+    fn categories() {
+        let event: Event = KeyPress::new(KeyCode::A, PhysicalKey::A).into();
+        assert!(event.is_in_category(EventCategory::INPUT));
+        assert!(event.is_in_category(EventCategory::KEYBOARD));
+        assert!(!event.is_in_category(EventCategory::MOUSE | EventCategory::WINDOW));
+        assert!(event
+            .category()
+            .contains(EventCategory::INPUT | EventCategory::KEYBOARD));
+
+        let mut dispatcher = EventDispatcher::new(&event);
+        assert!(!dispatcher.dispatch_category(EventCategory::WINDOW, |_| true));
+        assert!(dispatcher.dispatch_category(EventCategory::INPUT, |_| false));
+        assert!(!dispatcher.handled());
+    }
+
+    #[test]
+    fn queue_handles_share_events() {
+        let queue = EventQueue::new();
+        let handle = queue.clone();
+        handle.post(KeyPress::new(KeyCode::A, PhysicalKey::A));
+        queue.post(WindowClose);
+
+        let events: Vec<Event> = queue.take().into();
+        assert_eq!(
+            events,
+            [
+                KeyPress::new(KeyCode::A, PhysicalKey::A).into(),
+                WindowClose.into()
+            ]
+        );
+        assert!(handle.is_empty());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct LevelLoaded(u32);
+
+    #[test]
+    fn user_events_dispatch_by_payload() {
+        let (sender, receiver) = mpsc::channel();
+        let proxy = EventProxy::from(sender);
+        std::thread::spawn(move || proxy.send(LevelLoaded(3)).unwrap())
+            .join()
+            .unwrap();
+
+        let event: Event = receiver.recv().unwrap().into();
+        assert!(event.is_in_category(EventCategory::APPLICATION));
+        let mut dispatcher = EventDispatcher::new(&event);
+        assert!(!dispatcher.dispatch_user::<String>(|_| panic!("not a string")));
+        let mut level = None;
+        assert!(dispatcher.dispatch_user::<LevelLoaded>(|e| {
+            level = Some(e.0);
+            true
+        }));
+        assert_eq!(level, Some(3));
+        assert!(dispatcher.handled());
+    }
 }
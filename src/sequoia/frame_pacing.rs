@@ -0,0 +1,205 @@
+use std::time::{Duration, Instant};
+
+use glium::glutin::{self, event_loop::ControlFlow};
+
+/// When the application draws a new frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FramePacing {
+    /// Draw once per refresh of the monitor. Needs a display created with
+    /// `context_builder`, since vsync can't be turned on afterwards.
+    VSync,
+    /// Draw at most `fps` frames per second, sleeping in between. `fps` is
+    /// clamped between `MIN_CAPPED_FPS` and `MAX_CAPPED_FPS`.
+    Capped { fps: f64 },
+    /// Draw as fast as possible.
+    Uncapped,
+    /// Only draw after input, or when the ui asks for it. Meant for tools that
+    /// sit idle most of the time.
+    Reactive,
+}
+
+impl FramePacing {
+    /// The lowest frame cap. Lower caps and NaN are clamped to it.
+    pub const MIN_CAPPED_FPS: f64 = 1.0;
+    /// The highest frame cap. Higher caps are clamped to it.
+    pub const MAX_CAPPED_FPS: f64 = 10_000.0;
+
+    /// Starts the context of the display, with vsync on for `VSync`. With
+    /// vsync on, the other modes can't draw faster than the monitor either.
+    pub fn context_builder<'a>(self) -> glutin::ContextBuilder<'a, glutin::NotCurrent> {
+        glutin::ContextBuilder::new().with_vsync(self == FramePacing::VSync)
+    }
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        FramePacing::Capped { fps: 60.0 }
+    }
+}
+
+/// Decides when to draw according to a `FramePacing`, and what the event loop
+/// does in between.
+#[derive(Debug)]
+pub struct FramePacer {
+    pacing: FramePacing,
+    next_frame: Instant,
+    redraw_requested: bool,
+    repaint_at: Option<Instant>,
+}
+
+impl FramePacer {
+    pub fn new(pacing: FramePacing) -> Self {
+        Self {
+            pacing: clamp_fps(pacing),
+            next_frame: Instant::now(),
+            // always draw the first frame
+            redraw_requested: true,
+            repaint_at: None,
+        }
+    }
+
+    pub fn pacing(&self) -> FramePacing {
+        self.pacing
+    }
+
+    pub fn set_pacing(&mut self, pacing: FramePacing) {
+        self.pacing = clamp_fps(pacing);
+        self.next_frame = Instant::now();
+        self.redraw_requested = true;
+    }
+
+    /// Asks for a frame in `Reactive` mode, like after input. The other modes
+    /// draw anyway.
+    pub fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+    }
+
+    /// Asks for a frame `delay` from now in `Reactive` mode, like the ui does
+    /// for animations and tooltips.
+    pub fn request_redraw_after(&mut self, delay: Duration) {
+        if delay.is_zero() {
+            self.redraw_requested = true;
+        } else if let Some(at) = Instant::now().checked_add(delay) {
+            self.repaint_at = Some(self.repaint_at.map_or(at, |current| current.min(at)));
+        }
+    }
+
+    /// Whether a frame should be drawn at `now`. Called once all pending
+    /// events are handled.
+    pub fn should_draw(&self, now: Instant) -> bool {
+        match self.pacing {
+            FramePacing::VSync | FramePacing::Uncapped => true,
+            FramePacing::Capped { .. } => now >= self.next_frame,
+            FramePacing::Reactive => {
+                self.redraw_requested || self.repaint_at.is_some_and(|at| now >= at)
+            }
+        }
+    }
+
+    /// Records that a frame was drawn at `now` and schedules the next one.
+    pub fn on_frame(&mut self, now: Instant) {
+        if let FramePacing::Capped { fps } = self.pacing {
+            let interval = Duration::from_secs_f64(1.0 / fps);
+            self.next_frame += interval;
+            // don't try to catch up on frames that were missed
+            if self.next_frame < now {
+                self.next_frame = now + interval;
+            }
+        }
+        self.redraw_requested = false;
+        if self.repaint_at.is_some_and(|at| now >= at) {
+            self.repaint_at = None;
+        }
+    }
+
+    /// What the event loop should do until the next frame.
+    pub fn control_flow(&self) -> ControlFlow {
+        match self.pacing {
+            FramePacing::VSync | FramePacing::Uncapped => ControlFlow::Poll,
+            FramePacing::Capped { .. } => ControlFlow::WaitUntil(self.next_frame),
+            FramePacing::Reactive if self.redraw_requested => ControlFlow::Poll,
+            FramePacing::Reactive => match self.repaint_at {
+                Some(at) => ControlFlow::WaitUntil(at),
+                None => ControlFlow::Wait,
+            },
+        }
+    }
+}
+
+fn clamp_fps(pacing: FramePacing) -> FramePacing {
+    let FramePacing::Capped { fps } = pacing else {
+        return pacing;
+    };
+    let clamped = if fps.is_nan() {
+        FramePacing::MIN_CAPPED_FPS
+    } else {
+        fps.clamp(FramePacing::MIN_CAPPED_FPS, FramePacing::MAX_CAPPED_FPS)
+    };
+    if clamped != fps {
+        warn!("Frame cap {} is out of range, using {}", fps, clamped);
+    }
+    FramePacing::Capped { fps: clamped }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capped_waits_for_the_next_frame() {
+        let mut pacer = FramePacer::new(FramePacing::Capped { fps: 50.0 });
+        let start = pacer.next_frame;
+        assert!(pacer.should_draw(start));
+        pacer.on_frame(start);
+        assert!(!pacer.should_draw(start + Duration::from_millis(10)));
+        assert!(pacer.should_draw(start + Duration::from_millis(20)));
+        assert_eq!(
+            pacer.control_flow(),
+            ControlFlow::WaitUntil(start + Duration::from_millis(20))
+        );
+
+        // a late frame schedules the next one from now
+        let late = start + Duration::from_millis(100);
+        pacer.on_frame(late);
+        assert_eq!(
+            pacer.control_flow(),
+            ControlFlow::WaitUntil(late + Duration::from_millis(20))
+        );
+    }
+
+    #[test]
+    fn capped_clamps_fps() {
+        let mut pacer = FramePacer::new(FramePacing::Capped { fps: 0.0 });
+        assert_eq!(pacer.pacing(), FramePacing::Capped { fps: 1.0 });
+        let start = pacer.next_frame;
+        pacer.on_frame(start);
+        assert_eq!(
+            pacer.control_flow(),
+            ControlFlow::WaitUntil(start + Duration::from_secs(1))
+        );
+
+        pacer.set_pacing(FramePacing::Capped { fps: f64::NAN });
+        assert_eq!(pacer.pacing(), FramePacing::Capped { fps: 1.0 });
+        pacer.on_frame(Instant::now());
+    }
+
+    #[test]
+    fn vsync_builds_a_vsync_context() {
+        assert!(FramePacing::VSync.context_builder().gl_attr.vsync);
+        assert!(!FramePacing::Uncapped.context_builder().gl_attr.vsync);
+    }
+
+    #[test]
+    fn reactive_only_draws_when_asked() {
+        let mut pacer = FramePacer::new(FramePacing::Reactive);
+        let now = Instant::now();
+        pacer.on_frame(now);
+        assert!(!pacer.should_draw(now));
+        assert_eq!(pacer.control_flow(), ControlFlow::Wait);
+
+        pacer.request_redraw();
+        assert!(pacer.should_draw(now));
+        pacer.on_frame(now);
+        assert!(!pacer.should_draw(now));
+    }
+}
@@ -1,10 +1,224 @@
 use std::collections::HashSet;
 
-use super::{keycode::KeyCode, mousecode::MouseCode};
+use super::{
+    event::{self, Event},
+    keycode::{KeyCode, Modifiers},
+    mousecode::MouseCode,
+    physical_key::PhysicalKey,
+};
 
+/// The state of the keyboard and mouse, as of the events dispatched so far.
+/// Besides what is held down, it keeps what changed during the current
+/// frame, for toggles like "press F to open the menu".
+///
+/// The "this frame" state is the same in `on_fixed_update` and `on_update`,
+/// so a press can be seen by several fixed updates, or by none when a frame
+/// runs none.
+#[derive(Debug, Default)]
 pub struct Input {
     pub keys_pressed: HashSet<KeyCode>,
+    /// The same keys by where they are on the keyboard.
+    pub physical_keys_pressed: HashSet<PhysicalKey>,
     pub mouse_pressed: HashSet<MouseCode>,
     pub mouse_location: (f64, f64),
+    /// The modifier keys held down right now.
+    pub modifiers: Modifiers,
+    keys_pressed_this_frame: HashSet<KeyCode>,
+    keys_released_this_frame: HashSet<KeyCode>,
+    physical_keys_pressed_this_frame: HashSet<PhysicalKey>,
+    physical_keys_released_this_frame: HashSet<PhysicalKey>,
+    mouse_pressed_this_frame: HashSet<MouseCode>,
+    mouse_released_this_frame: HashSet<MouseCode>,
+    mouse_delta: (f64, f64),
+    scroll_delta: (f64, f64),
+    /// Whether `mouse_location` is where the cursor is, rather than where it
+    /// was before it left the window.
+    mouse_tracked: bool,
 }
 
+impl Input {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_key_down(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    /// Whether `key` went down this frame. Holding it doesn't count again.
+    pub fn was_key_pressed_this_frame(&self, key: KeyCode) -> bool {
+        self.keys_pressed_this_frame.contains(&key)
+    }
+
+    pub fn was_key_released_this_frame(&self, key: KeyCode) -> bool {
+        self.keys_released_this_frame.contains(&key)
+    }
+
+    pub fn is_physical_key_down(&self, key: PhysicalKey) -> bool {
+        self.physical_keys_pressed.contains(&key)
+    }
+
+    pub fn was_physical_key_pressed_this_frame(&self, key: PhysicalKey) -> bool {
+        self.physical_keys_pressed_this_frame.contains(&key)
+    }
+
+    pub fn was_physical_key_released_this_frame(&self, key: PhysicalKey) -> bool {
+        self.physical_keys_released_this_frame.contains(&key)
+    }
+
+    pub fn is_mouse_button_down(&self, button: MouseCode) -> bool {
+        self.mouse_pressed.contains(&button)
+    }
+
+    pub fn was_mouse_button_pressed_this_frame(&self, button: MouseCode) -> bool {
+        self.mouse_pressed_this_frame.contains(&button)
+    }
+
+    pub fn was_mouse_button_released_this_frame(&self, button: MouseCode) -> bool {
+        self.mouse_released_this_frame.contains(&button)
+    }
+
+    /// How far the mouse moved this frame, in window pixels.
+    pub fn mouse_delta(&self) -> (f64, f64) {
+        self.mouse_delta
+    }
+
+    /// How far the mouse wheel scrolled this frame, added up.
+    pub fn scroll_delta(&self) -> (f64, f64) {
+        self.scroll_delta
+    }
+
+    /// Forgets what changed during the previous frame. The application calls
+    /// this at the start of every frame, before dispatching its events.
+    pub fn begin_frame(&mut self) {
+        self.keys_pressed_this_frame.clear();
+        self.keys_released_this_frame.clear();
+        self.physical_keys_pressed_this_frame.clear();
+        self.physical_keys_released_this_frame.clear();
+        self.mouse_pressed_this_frame.clear();
+        self.mouse_released_this_frame.clear();
+        self.mouse_delta = (0.0, 0.0);
+        self.scroll_delta = (0.0, 0.0);
+    }
+
+    /// Updates the state with `event`.
+    pub fn on_event(&mut self, event: &Event) {
+        match event {
+            Event::KeyPress(event::KeyPress {
+                key, physical_key, ..
+            }) => {
+                if self.keys_pressed.insert(*key) {
+                    self.keys_pressed_this_frame.insert(*key);
+                }
+                if self.physical_keys_pressed.insert(*physical_key) {
+                    self.physical_keys_pressed_this_frame.insert(*physical_key);
+                }
+            }
+            Event::KeyRelease(event::KeyRelease { key, physical_key }) => {
+                if self.keys_pressed.remove(key) {
+                    self.keys_released_this_frame.insert(*key);
+                }
+                if self.physical_keys_pressed.remove(physical_key) {
+                    self.physical_keys_released_this_frame.insert(*physical_key);
+                }
+            }
+            Event::ModifiersChange(event::ModifiersChange { modifiers }) => {
+                self.modifiers = *modifiers;
+            }
+            Event::MouseButtonPress(event::MouseButtonPress { mouse_button }) => {
+                let was_up = self.mouse_pressed.insert(*mouse_button);
+                if was_up {
+                    self.mouse_pressed_this_frame.insert(*mouse_button);
+                }
+            }
+            Event::MouseButtonRelease(event::MouseButtonRelease { mouse_button }) => {
+                let was_down = self.mouse_pressed.remove(mouse_button);
+                if was_down {
+                    self.mouse_released_this_frame.insert(*mouse_button);
+                }
+            }
+            Event::MouseScroll(event::MouseScroll { x, y }) => {
+                self.scroll_delta.0 += x;
+                self.scroll_delta.1 += y;
+            }
+            Event::MouseMove(event::MouseMove { x, y }) => {
+                // the first position after entering is not a movement
+                if self.mouse_tracked {
+                    self.mouse_delta.0 += x - self.mouse_location.0;
+                    self.mouse_delta.1 += y - self.mouse_location.1;
+                }
+                self.mouse_location = (*x, *y);
+                self.mouse_tracked = true;
+            }
+            Event::MouseLeave(_) => self.mouse_tracked = false,
+            Event::WindowUnfocus(_) => {
+                // the window won't hear about releases while unfocused, so
+                // nothing would ever release these
+                self.keys_released_this_frame
+                    .extend(self.keys_pressed.drain());
+                self.mouse_released_this_frame
+                    .extend(self.mouse_pressed.drain());
+                self.physical_keys_released_this_frame
+                    .extend(self.physical_keys_pressed.drain());
+                self.modifiers = Modifiers::NONE;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_last_one_frame() {
+        let mut input = Input::new();
+        let press = event::KeyPress::new(KeyCode::F, PhysicalKey::F);
+        input.on_event(&press.into());
+        assert!(input.is_key_down(KeyCode::F));
+        assert!(input.was_key_pressed_this_frame(KeyCode::F));
+
+        // holding the key repeats the press
+        input.begin_frame();
+        input.on_event(&press.into());
+        assert!(input.is_key_down(KeyCode::F));
+        assert!(!input.was_key_pressed_this_frame(KeyCode::F));
+
+        input.begin_frame();
+        input.on_event(
+            &event::MouseButtonPress {
+                mouse_button: MouseCode::Button0,
+            }
+            .into(),
+        );
+        input.on_event(
+            &event::MouseButtonRelease {
+                mouse_button: MouseCode::Button0,
+            }
+            .into(),
+        );
+        assert!(!input.is_mouse_button_down(MouseCode::Button0));
+        assert!(input.was_mouse_button_pressed_this_frame(MouseCode::Button0));
+        assert!(input.was_mouse_button_released_this_frame(MouseCode::Button0));
+        input.on_event(&event::WindowUnfocus.into());
+        assert!(input.was_key_released_this_frame(KeyCode::F));
+    }
+
+    #[test]
+    fn mouse_deltas_add_up() {
+        let mut input = Input::new();
+        input.on_event(&event::MouseMove { x: 100.0, y: 50.0 }.into());
+        assert_eq!(input.mouse_delta(), (0.0, 0.0));
+        input.on_event(&event::MouseMove { x: 103.0, y: 49.0 }.into());
+        input.on_event(&event::MouseMove { x: 110.0, y: 45.0 }.into());
+        input.on_event(&event::MouseScroll { x: 0.0, y: 1.0 }.into());
+        input.on_event(&event::MouseScroll { x: 0.0, y: 2.0 }.into());
+        assert_eq!(input.mouse_delta(), (10.0, -5.0));
+        assert_eq!(input.scroll_delta(), (0.0, 3.0));
+
+        input.begin_frame();
+        assert_eq!(input.mouse_delta(), (0.0, 0.0));
+        assert_eq!(input.scroll_delta(), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,716 @@
+use std::{collections::BTreeMap, fmt, io, path::Path, str::FromStr};
+
+use super::{
+    input::Input,
+    keycode::{KeyCode, ParseKeyError},
+    mousecode::MouseCode,
+    physical_key::PhysicalKey,
+};
+
+/// Something that is either held down or not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button {
+    /// A key by its label in the current layout, for shortcuts like Ctrl+S.
+    Key(KeyCode),
+    /// A key by where it is on the keyboard, for movement.
+    PhysicalKey(PhysicalKey),
+    Mouse(MouseCode),
+}
+
+impl Button {
+    pub fn is_down(self, input: &Input) -> bool {
+        match self {
+            Button::Key(key) => input.is_key_down(key),
+            Button::PhysicalKey(key) => input.is_physical_key_down(key),
+            Button::Mouse(button) => input.is_mouse_button_down(button),
+        }
+    }
+
+    pub fn was_pressed_this_frame(self, input: &Input) -> bool {
+        match self {
+            Button::Key(key) => input.was_key_pressed_this_frame(key),
+            Button::PhysicalKey(key) => input.was_physical_key_pressed_this_frame(key),
+            Button::Mouse(button) => input.was_mouse_button_pressed_this_frame(button),
+        }
+    }
+
+    pub fn was_released_this_frame(self, input: &Input) -> bool {
+        match self {
+            Button::Key(key) => input.was_key_released_this_frame(key),
+            Button::PhysicalKey(key) => input.was_physical_key_released_this_frame(key),
+            Button::Mouse(button) => input.was_mouse_button_released_this_frame(button),
+        }
+    }
+}
+
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Button::Key(key) => write!(f, "Key({})", key),
+            Button::PhysicalKey(key) => write!(f, "PhysicalKey({})", key),
+            Button::Mouse(button) => write!(f, "Mouse({})", button),
+        }
+    }
+}
+
+/// A value along one axis, for things like zooming or turning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    /// -1 while `negative` is held, 1 while `positive` is and 0 for both or
+    /// neither.
+    Buttons {
+        negative: Button,
+        positive: Button,
+    },
+    /// How far the mouse wheel scrolled this frame.
+    ScrollX,
+    ScrollY,
+    /// How far the mouse moved this frame in pixels, with y up.
+    MouseX,
+    MouseY,
+}
+
+impl Axis {
+    pub fn value(self, input: &Input) -> f32 {
+        match self {
+            Axis::Buttons { negative, positive } => {
+                positive.is_down(input) as i32 as f32 - negative.is_down(input) as i32 as f32
+            }
+            Axis::ScrollX => input.scroll_delta().0 as f32,
+            Axis::ScrollY => input.scroll_delta().1 as f32,
+            Axis::MouseX => input.mouse_delta().0 as f32,
+            Axis::MouseY => -input.mouse_delta().1 as f32,
+        }
+    }
+}
+
+impl fmt::Display for Axis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Axis::Buttons { negative, positive } => {
+                write!(f, "Buttons({}, {})", negative, positive)
+            }
+            Axis::ScrollX => f.write_str("ScrollX"),
+            Axis::ScrollY => f.write_str("ScrollY"),
+            Axis::MouseX => f.write_str("MouseX"),
+            Axis::MouseY => f.write_str("MouseY"),
+        }
+    }
+}
+
+/// A value in two dimensions with y up, for things like moving or looking
+/// around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis2d {
+    /// A unit in the direction of each button held, normalized so diagonals
+    /// aren't faster.
+    Buttons {
+        up: Button,
+        down: Button,
+        left: Button,
+        right: Button,
+    },
+    /// How far the mouse wheel scrolled this frame.
+    Scroll,
+    /// How far the mouse moved this frame in pixels.
+    MouseDelta,
+}
+
+impl Axis2d {
+    /// W, A, S and D by position, so ZQSD on an AZERTY keyboard.
+    pub fn wasd() -> Self {
+        Axis2d::Buttons {
+            up: Button::PhysicalKey(PhysicalKey::W),
+            down: Button::PhysicalKey(PhysicalKey::S),
+            left: Button::PhysicalKey(PhysicalKey::A),
+            right: Button::PhysicalKey(PhysicalKey::D),
+        }
+    }
+
+    pub fn arrow_keys() -> Self {
+        Axis2d::Buttons {
+            up: Button::Key(KeyCode::Up),
+            down: Button::Key(KeyCode::Down),
+            left: Button::Key(KeyCode::Left),
+            right: Button::Key(KeyCode::Right),
+        }
+    }
+
+    pub fn value(self, input: &Input) -> glam::Vec2 {
+        match self {
+            Axis2d::Buttons {
+                up,
+                down,
+                left,
+                right,
+            } => {
+                let x = Axis::Buttons {
+                    negative: left,
+                    positive: right,
+                };
+                let y = Axis::Buttons {
+                    negative: down,
+                    positive: up,
+                };
+                glam::vec2(x.value(input), y.value(input)).normalize_or_zero()
+            }
+            Axis2d::Scroll => {
+                let (x, y) = input.scroll_delta();
+                glam::vec2(x as f32, y as f32)
+            }
+            Axis2d::MouseDelta => {
+                let (x, y) = input.mouse_delta();
+                glam::vec2(x as f32, -y as f32)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Axis2d {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Axis2d::Buttons {
+                up,
+                down,
+                left,
+                right,
+            } => write!(f, "Buttons({}, {}, {}, {})", up, down, left, right),
+            Axis2d::Scroll => f.write_str("Scroll"),
+            Axis2d::MouseDelta => f.write_str("MouseDelta"),
+        }
+    }
+}
+
+/// Named actions and axes with what they are bound to, for one part of the
+/// game like walking around or a menu. Several bindings for one name all
+/// work, like the space bar and a mouse button both jumping.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputContext {
+    actions: BTreeMap<String, Vec<Button>>,
+    axes: BTreeMap<String, Vec<Axis>>,
+    axes_2d: BTreeMap<String, Vec<Axis2d>>,
+}
+
+impl InputContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `button` to the bindings of `action`.
+    pub fn with_action(mut self, action: &str, button: Button) -> Self {
+        self.actions
+            .entry(action.to_owned())
+            .or_default()
+            .push(button);
+        self
+    }
+
+    pub fn with_axis(mut self, axis: &str, binding: Axis) -> Self {
+        self.axes.entry(axis.to_owned()).or_default().push(binding);
+        self
+    }
+
+    pub fn with_axis_2d(mut self, axis: &str, binding: Axis2d) -> Self {
+        self.axes_2d
+            .entry(axis.to_owned())
+            .or_default()
+            .push(binding);
+        self
+    }
+
+    /// Replaces the bindings of `action`, like when the player rebinds it.
+    /// No bindings leaves it unbound.
+    pub fn bind_action(&mut self, action: &str, buttons: Vec<Button>) {
+        self.actions.insert(action.to_owned(), buttons);
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, bindings: Vec<Axis>) {
+        self.axes.insert(axis.to_owned(), bindings);
+    }
+
+    pub fn bind_axis_2d(&mut self, axis: &str, bindings: Vec<Axis2d>) {
+        self.axes_2d.insert(axis.to_owned(), bindings);
+    }
+
+    pub fn action(&self, action: &str) -> Option<&[Button]> {
+        self.actions.get(action).map(Vec::as_slice)
+    }
+
+    pub fn axis(&self, axis: &str) -> Option<&[Axis]> {
+        self.axes.get(axis).map(Vec::as_slice)
+    }
+
+    pub fn axis_2d(&self, axis: &str) -> Option<&[Axis2d]> {
+        self.axes_2d.get(axis).map(Vec::as_slice)
+    }
+}
+
+#[derive(Debug)]
+pub enum InputMapError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for InputMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputMapError::Io(why) => write!(f, "could not read or write bindings: {}", why),
+            InputMapError::Parse { line, message } => {
+                write!(f, "invalid bindings on line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InputMapError {}
+
+impl From<io::Error> for InputMapError {
+    fn from(why: io::Error) -> Self {
+        InputMapError::Io(why)
+    }
+}
+
+/// Answers whether actions happened and where axes point, so gameplay code
+/// asks for "jump" instead of checking the space bar. Contexts are added
+/// once and then pushed and popped, like a layer pushing its own in
+/// `on_attach` and popping it in `on_detach`.
+///
+/// ```ignore
+/// let mut input_map = InputMap::new();
+/// input_map.add_context(
+///     "walking",
+///     InputContext::new()
+///         .with_action("jump", Button::Key(KeyCode::Space))
+///         .with_axis_2d("move", Axis2d::wasd()),
+/// );
+/// input_map.push_context("walking");
+/// // let players rebind them
+/// input_map.load("bindings.cfg")?;
+///
+/// // in on_update
+/// if input_map.was_action_pressed_this_frame(input, "jump") { ... }
+/// let direction = input_map.axis_2d(input, "move");
+/// ```
+///
+/// The config file has a section per context, and a line per action or axis
+/// with its bindings separated by `|`:
+///
+/// ```text
+/// [walking]
+/// action jump = Key(Space) | Mouse(Button0)
+/// axis zoom = ScrollY
+/// axis2d move = Buttons(PhysicalKey(W), PhysicalKey(S), PhysicalKey(A), PhysicalKey(D))
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InputMap {
+    contexts: BTreeMap<String, InputContext>,
+    /// Names of the active contexts, the top one last.
+    active: Vec<String>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces the context called `name`. It does nothing until it
+    /// is pushed.
+    pub fn add_context(&mut self, name: &str, context: InputContext) {
+        self.contexts.insert(name.to_owned(), context);
+    }
+
+    pub fn context(&self, name: &str) -> Option<&InputContext> {
+        self.contexts.get(name)
+    }
+
+    pub fn context_mut(&mut self, name: &str) -> Option<&mut InputContext> {
+        self.contexts.get_mut(name)
+    }
+
+    /// Activates the context called `name` on top of the others. Its
+    /// bindings hide those with the same name below it, like a menu taking
+    /// over "confirm" while it is open.
+    pub fn push_context(&mut self, name: &str) {
+        if !self.contexts.contains_key(name) {
+            warn!("Pushing input context {}, which has no bindings", name);
+        }
+        self.active.push(name.to_owned());
+    }
+
+    /// Deactivates the topmost context called `name`.
+    pub fn pop_context(&mut self, name: &str) {
+        if let Some(index) = self.active.iter().rposition(|active| active == name) {
+            self.active.remove(index);
+        }
+    }
+
+    /// Names of the active contexts, the top one first.
+    pub fn active_contexts(&self) -> impl Iterator<Item = &str> {
+        self.active.iter().rev().map(String::as_str)
+    }
+
+    /// The bindings from the topmost active context that has any.
+    fn bindings<T>(&self, get: impl Fn(&InputContext) -> Option<&[T]>) -> &[T] {
+        self.active_contexts()
+            .filter_map(|name| self.contexts.get(name))
+            .find_map(get)
+            .unwrap_or(&[])
+    }
+
+    pub fn is_action_down(&self, input: &Input, action: &str) -> bool {
+        self.bindings(|context| context.action(action))
+            .iter()
+            .any(|button| button.is_down(input))
+    }
+
+    pub fn was_action_pressed_this_frame(&self, input: &Input, action: &str) -> bool {
+        self.bindings(|context| context.action(action))
+            .iter()
+            .any(|button| button.was_pressed_this_frame(input))
+    }
+
+    /// Whether the last binding holding `action` down was released this
+    /// frame.
+    pub fn was_action_released_this_frame(&self, input: &Input, action: &str) -> bool {
+        let buttons = self.bindings(|context| context.action(action));
+        buttons
+            .iter()
+            .any(|button| button.was_released_this_frame(input))
+            && !buttons.iter().any(|button| button.is_down(input))
+    }
+
+    /// The sum of all bindings of `axis`, 0 when it has none.
+    pub fn axis(&self, input: &Input, axis: &str) -> f32 {
+        self.bindings(|context| context.axis(axis))
+            .iter()
+            .map(|binding| binding.value(input))
+            .sum()
+    }
+
+    pub fn axis_2d(&self, input: &Input, axis: &str) -> glam::Vec2 {
+        self.bindings(|context| context.axis_2d(axis))
+            .iter()
+            .fold(glam::Vec2::ZERO, |sum, binding| sum + binding.value(input))
+    }
+
+    /// Replaces bindings with those in the config file at `path`, keeping the
+    /// ones it doesn't mention. Nothing changes when the file is invalid.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<(), InputMapError> {
+        let text = std::fs::read_to_string(path)?;
+        self.load_str(&text)
+    }
+
+    /// Like `load`, from the contents of a config file.
+    pub fn load_str(&mut self, text: &str) -> Result<(), InputMapError> {
+        let mut contexts = self.contexts.clone();
+        let mut context = None;
+
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| InputMapError::Parse {
+                line: index + 1,
+                message,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let name = name.trim().to_owned();
+                contexts.entry(name.clone()).or_default();
+                context = Some(name);
+                continue;
+            }
+
+            let Some(context) = context.as_ref().and_then(|name| contexts.get_mut(name)) else {
+                return Err(error("bindings before the first [context]".to_owned()));
+            };
+            let Some((left, right)) = line.split_once('=') else {
+                return Err(error(format!("expected `=` in `{}`", line)));
+            };
+            let Some((kind, name)) = left.trim().split_once(char::is_whitespace) else {
+                return Err(error(format!("expected a kind and a name in `{}`", left)));
+            };
+            let name = name.trim();
+            let terms = if right.trim().is_empty() {
+                Vec::new()
+            } else {
+                split_top_level(right, '|')
+                    .into_iter()
+                    .map(parse_term)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?
+            };
+
+            match kind {
+                "action" => {
+                    let buttons = terms.iter().map(parse_button).collect::<Result<_, _>>();
+                    context.bind_action(name, buttons.map_err(error)?);
+                }
+                "axis" => {
+                    let axes = terms.iter().map(parse_axis).collect::<Result<_, _>>();
+                    context.bind_axis(name, axes.map_err(error)?);
+                }
+                "axis2d" => {
+                    let axes = terms.iter().map(parse_axis_2d).collect::<Result<_, _>>();
+                    context.bind_axis_2d(name, axes.map_err(error)?);
+                }
+                _ => {
+                    return Err(error(format!(
+                        "expected `action`, `axis` or `axis2d`, found `{}`",
+                        kind
+                    )))
+                }
+            }
+        }
+
+        self.contexts = contexts;
+        Ok(())
+    }
+
+    /// Writes the bindings of every context to a config file at `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), InputMapError> {
+        std::fs::write(path, self.to_string())?;
+        Ok(())
+    }
+}
+
+/// The bindings in the config file format.
+impl fmt::Display for InputMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_bindings<T: fmt::Display>(
+            f: &mut fmt::Formatter<'_>,
+            kind: &str,
+            bindings: &BTreeMap<String, Vec<T>>,
+        ) -> fmt::Result {
+            for (name, bindings) in bindings {
+                let bindings: Vec<String> = bindings.iter().map(T::to_string).collect();
+                writeln!(f, "{} {} = {}", kind, name, bindings.join(" | "))?;
+            }
+            Ok(())
+        }
+
+        for (index, (name, context)) in self.contexts.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "[{}]", name)?;
+            write_bindings(f, "action", &context.actions)?;
+            write_bindings(f, "axis", &context.axes)?;
+            write_bindings(f, "axis2d", &context.axes_2d)?;
+        }
+        Ok(())
+    }
+}
+
+/// `Name` or `Name(argument, ...)`, which every binding is written as.
+struct Term<'a> {
+    name: &'a str,
+    arguments: Vec<Term<'a>>,
+}
+
+impl fmt::Display for Term<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.arguments.is_empty() {
+            let arguments: Vec<String> = self.arguments.iter().map(Term::to_string).collect();
+            write!(f, "({})", arguments.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_term(text: &str) -> Result<Term<'_>, String> {
+    let text = text.trim();
+    let Some(open) = text.find('(') else {
+        if text.is_empty() || text.contains(')') || text.contains(',') {
+            return Err(format!("expected a name, found `{}`", text));
+        }
+        return Ok(Term {
+            name: text,
+            arguments: Vec::new(),
+        });
+    };
+    let Some(inner) = text[open + 1..].strip_suffix(')') else {
+        return Err(format!("expected `)` at the end of `{}`", text));
+    };
+    Ok(Term {
+        name: text[..open].trim(),
+        arguments: split_top_level(inner, ',')
+            .into_iter()
+            .map(parse_term)
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+/// Splits `text` at each `separator` outside of parentheses.
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if c == separator && depth == 0 => {
+                parts.push(&text[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// A key or button like `Space`, or a key without a name like `Unknown(42)`.
+fn parse_key<T: FromStr<Err = ParseKeyError>>(term: &Term) -> Result<T, String> {
+    term.to_string()
+        .parse()
+        .map_err(|why: ParseKeyError| why.to_string())
+}
+
+fn parse_button(term: &Term) -> Result<Button, String> {
+    match (term.name, term.arguments.as_slice()) {
+        ("Key", [key]) => parse_key(key).map(Button::Key),
+        ("PhysicalKey", [key]) => parse_key(key).map(Button::PhysicalKey),
+        ("Mouse", [button]) => parse_key(button).map(Button::Mouse),
+        _ => Err(format!(
+            "expected `Key(..)`, `PhysicalKey(..)` or `Mouse(..)`, found `{}`",
+            term
+        )),
+    }
+}
+
+fn parse_axis(term: &Term) -> Result<Axis, String> {
+    match (term.name, term.arguments.as_slice()) {
+        ("Buttons", [negative, positive]) => Ok(Axis::Buttons {
+            negative: parse_button(negative)?,
+            positive: parse_button(positive)?,
+        }),
+        ("ScrollX", []) => Ok(Axis::ScrollX),
+        ("ScrollY", []) => Ok(Axis::ScrollY),
+        ("MouseX", []) => Ok(Axis::MouseX),
+        ("MouseY", []) => Ok(Axis::MouseY),
+        _ => Err(format!(
+            "expected `Buttons(negative, positive)`, `ScrollX`, `ScrollY`, `MouseX` or `MouseY`, found `{}`",
+            term
+        )),
+    }
+}
+
+fn parse_axis_2d(term: &Term) -> Result<Axis2d, String> {
+    match (term.name, term.arguments.as_slice()) {
+        ("Buttons", [up, down, left, right]) => Ok(Axis2d::Buttons {
+            up: parse_button(up)?,
+            down: parse_button(down)?,
+            left: parse_button(left)?,
+            right: parse_button(right)?,
+        }),
+        ("Scroll", []) => Ok(Axis2d::Scroll),
+        ("MouseDelta", []) => Ok(Axis2d::MouseDelta),
+        _ => Err(format!(
+            "expected `Buttons(up, down, left, right)`, `Scroll` or `MouseDelta`, found `{}`",
+            term
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequoia::event;
+
+    fn walking() -> InputMap {
+        let mut input_map = InputMap::new();
+        input_map.add_context(
+            "walking",
+            InputContext::new()
+                .with_action("jump", Button::Key(KeyCode::Space))
+                .with_action("jump", Button::Mouse(MouseCode::Button0))
+                .with_axis("zoom", Axis::ScrollY)
+                .with_axis_2d("move", Axis2d::wasd()),
+        );
+        input_map.add_context(
+            "menu",
+            InputContext::new().with_action("jump", Button::Key(KeyCode::Enter)),
+        );
+        input_map.push_context("walking");
+        input_map
+    }
+
+    #[test]
+    fn actions_and_axes_follow_the_top_context() {
+        let mut input_map = walking();
+        let mut input = Input::new();
+        input.on_event(&event::KeyPress::new(KeyCode::Space, PhysicalKey::Space).into());
+        input.on_event(&event::KeyPress::new(KeyCode::Z, PhysicalKey::W).into());
+        input.on_event(&event::KeyPress::new(KeyCode::D, PhysicalKey::D).into());
+
+        assert!(input_map.is_action_down(&input, "jump"));
+        assert!(input_map.was_action_pressed_this_frame(&input, "jump"));
+        let direction = input_map.axis_2d(&input, "move");
+        assert!((direction - glam::vec2(1.0, 1.0).normalize()).length() < 1e-6);
+        assert_eq!(input_map.axis(&input, "zoom"), 0.0);
+        assert_eq!(input_map.axis(&input, "missing"), 0.0);
+
+        // the menu's jump hides the one below, the rest still works
+        input_map.push_context("menu");
+        assert!(!input_map.is_action_down(&input, "jump"));
+        assert_ne!(input_map.axis_2d(&input, "move"), glam::Vec2::ZERO);
+        input_map.pop_context("menu");
+        assert!(input_map.is_action_down(&input, "jump"));
+    }
+
+    #[test]
+    fn bindings_round_trip_through_config() {
+        let input_map = walking();
+        let config = input_map.to_string();
+        assert!(config.contains("action jump = Key(Space) | Mouse(Button0)"));
+
+        let mut loaded = InputMap::new();
+        loaded.load_str(&config).unwrap();
+        assert_eq!(loaded.context("walking"), input_map.context("walking"));
+        assert_eq!(loaded.context("menu"), input_map.context("menu"));
+
+        // rebinding keeps the rest
+        let mut rebound = walking();
+        rebound
+            .load_str("# rebound\n[walking]\naction jump = PhysicalKey(Unknown(200))\n")
+            .unwrap();
+        let walking = rebound.context("walking").unwrap();
+        assert_eq!(
+            walking.action("jump"),
+            Some(&[Button::PhysicalKey(PhysicalKey::Unknown(200))][..])
+        );
+        assert_eq!(walking.axis("zoom"), Some(&[Axis::ScrollY][..]));
+
+        let error = rebound.load_str("[walking]\naction jump = Key(Spacebar)\n");
+        assert!(matches!(error, Err(InputMapError::Parse { line: 2, .. })));
+    }
+
+    #[test]
+    fn every_key_round_trips_through_config() {
+        let buttons = KeyCode::ALL
+            .iter()
+            .map(|&key| Button::Key(key))
+            .chain(PhysicalKey::ALL.iter().map(|&key| Button::PhysicalKey(key)))
+            .chain(MouseCode::ALL.iter().map(|&button| Button::Mouse(button)))
+            .chain([
+                Button::Key(KeyCode::Unknown(42)),
+                Button::PhysicalKey(PhysicalKey::Unknown(42)),
+            ])
+            .collect::<Vec<_>>();
+        let mut input_map = InputMap::new();
+        let mut context = InputContext::new();
+        context.bind_action("any", buttons.clone());
+        input_map.add_context("all", context);
+
+        let mut loaded = InputMap::new();
+        loaded.load_str(&input_map.to_string()).unwrap();
+        assert_eq!(
+            loaded.context("all").unwrap().action("any"),
+            Some(buttons.as_slice())
+        );
+    }
+}
@@ -1,140 +1,328 @@
-use glium::glutin::event::{VirtualKeyCode, VirtualKeyCode::*};
-
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
-pub enum KeyCode {
-    Space = 32,
-    Apostrophe = 39, /* ' */
-    Comma = 44,      /* , */
-    Minus = 45,      /* - */
-    Period = 46,     /* . */
-    Slash = 47,      /* / */
-
-    D0 = 48, /* 0 */
-    D1 = 49, /* 1 */
-    D2 = 50, /* 2 */
-    D3 = 51, /* 3 */
-    D4 = 52, /* 4 */
-    D5 = 53, /* 5 */
-    D6 = 54, /* 6 */
-    D7 = 55, /* 7 */
-    D8 = 56, /* 8 */
-    D9 = 57, /* 9 */
-
-    Semicolon = 59, /* ; */
-    Equal = 61,     /* = */
-
-    A = 65,
-    B = 66,
-    C = 67,
-    D = 68,
-    E = 69,
-    F = 70,
-    G = 71,
-    H = 72,
-    I = 73,
-    J = 74,
-    K = 75,
-    L = 76,
-    M = 77,
-    N = 78,
-    O = 79,
-    P = 80,
-    Q = 81,
-    R = 82,
-    S = 83,
-    T = 84,
-    U = 85,
-    V = 86,
-    W = 87,
-    X = 88,
-    Y = 89,
-    Z = 90,
-
-    LeftBracket = 91,  /* [ */
-    Backslash = 92,    /* \ */
-    RightBracket = 93, /* ] */
-    GraveAccent = 96,  /* ` */
-
-    World1 = 161, /* non-US #1 */
-    World2 = 162, /* non-US #2 */
-
-    /* Function keys */
-    Escape = 256,
-    Enter = 257,
-    Tab = 258,
-    Backspace = 259,
-    Insert = 260,
-    Delete = 261,
-    Right = 262,
-    Left = 263,
-    Down = 264,
-    Up = 265,
-    PageUp = 266,
-    PageDown = 267,
-    Home = 268,
-    End = 269,
-    CapsLock = 280,
-    ScrollLock = 281,
-    NumLock = 282,
-    PrintScreen = 283,
-    Pause = 284,
-    F1 = 290,
-    F2 = 291,
-    F3 = 292,
-    F4 = 293,
-    F5 = 294,
-    F6 = 295,
-    F7 = 296,
-    F8 = 297,
-    F9 = 298,
-    F10 = 299,
-    F11 = 300,
-    F12 = 301,
-    F13 = 302,
-    F14 = 303,
-    F15 = 304,
-    F16 = 305,
-    F17 = 306,
-    F18 = 307,
-    F19 = 308,
-    F20 = 309,
-    F21 = 310,
-    F22 = 311,
-    F23 = 312,
-    F24 = 313,
-    F25 = 314,
-
-    /* Keypad */
-    KP0 = 320,
-    KP1 = 321,
-    KP2 = 322,
-    KP3 = 323,
-    KP4 = 324,
-    KP5 = 325,
-    KP6 = 326,
-    KP7 = 327,
-    KP8 = 328,
-    KP9 = 329,
-    KPDecimal = 330,
-    KPDivide = 331,
-    KPMultiply = 332,
-    KPSubtract = 333,
-    KPAdd = 334,
-    KPEnter = 335,
-    KPEqual = 336,
-
-    LeftShift = 340,
-    LeftControl = 341,
-    LeftAlt = 342,
-    LeftSuper = 343,
-    RightShift = 344,
-    RightControl = 345,
-    RightAlt = 346,
-    RightSuper = 347,
-    Menu = 348,
+use std::{fmt, ops::BitOr};
+
+use glium::glutin::event::{KeyboardInput, ModifiersState, VirtualKeyCode, VirtualKeyCode::*};
+
+/// Declares an enum of keys or buttons along with its names: `ALL` to list
+/// them, and `as_str`, `Display` and `FromStr` to write and read them, like in
+/// config files. Names are the variant names, so they can't get out of sync.
+///
+/// Keys without a name go in an `Unknown` variant holding their scancode,
+/// declared after the enum and named like `Unknown(42)`.
+macro_rules! named_keys {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident $(= $value:literal)?,)*
+        }
+        $(#[$unknown_meta:meta])*
+        Unknown($scancode:ty)
+    ) => {
+        $(#[$meta])*
+        pub enum $name {
+            $($(#[$variant_meta])* $variant $(= $value)?,)*
+            $(#[$unknown_meta])*
+            Unknown($scancode),
+        }
+
+        impl $name {
+            /// Every key with a name, which is all but `Unknown`.
+            pub const ALL: &[$name] = &[$($name::$variant),*];
+
+            /// The name of the variant, so "Unknown" for all unknown keys.
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => stringify!($variant),)*
+                    $name::Unknown(_) => "Unknown",
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $name::Unknown(scancode) => write!(f, "Unknown({})", scancode),
+                    _ => f.write_str(self.as_str()),
+                }
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = $crate::sequoia::keycode::ParseKeyError;
+
+            fn from_str(name: &str) -> Result<Self, Self::Err> {
+                let error = || $crate::sequoia::keycode::ParseKeyError(name.to_owned());
+                if let Some(scancode) = name
+                    .strip_prefix("Unknown(")
+                    .and_then(|rest| rest.strip_suffix(')'))
+                {
+                    return scancode.parse().map($name::Unknown).map_err(|_| error());
+                }
+                match name {
+                    $(stringify!($variant) => Ok($name::$variant),)*
+                    _ => Err(error()),
+                }
+            }
+        }
+    };
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident $(= $value:literal)?,)*
+        }
+    ) => {
+        $(#[$meta])*
+        pub enum $name {
+            $($(#[$variant_meta])* $variant $(= $value)?,)*
+        }
+
+        impl $name {
+            pub const ALL: &[$name] = &[$($name::$variant),*];
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => stringify!($variant),)*
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = $crate::sequoia::keycode::ParseKeyError;
+
+            fn from_str(name: &str) -> Result<Self, Self::Err> {
+                match name {
+                    $(stringify!($variant) => Ok($name::$variant),)*
+                    _ => Err($crate::sequoia::keycode::ParseKeyError(name.to_owned())),
+                }
+            }
+        }
+    };
+}
+
+/// A name that no key or button has, from `FromStr`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseKeyError(pub String);
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no key or button is called `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseKeyError {}
+
+named_keys! {
+    /// A key, by what it means in the current keyboard layout. The values of
+    /// the named keys follow GLFW, where it has them.
+    #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+    #[repr(u32)]
+    pub enum KeyCode {
+        Space = 32,
+        Apostrophe = 39, /* ' */
+        Comma = 44,      /* , */
+        Minus = 45,      /* - */
+        Period = 46,     /* . */
+        Slash = 47,      /* / */
+
+        D0 = 48, /* 0 */
+        D1 = 49, /* 1 */
+        D2 = 50, /* 2 */
+        D3 = 51, /* 3 */
+        D4 = 52, /* 4 */
+        D5 = 53, /* 5 */
+        D6 = 54, /* 6 */
+        D7 = 55, /* 7 */
+        D8 = 56, /* 8 */
+        D9 = 57, /* 9 */
+
+        Semicolon = 59, /* ; */
+        Equal = 61,     /* = */
+
+        A = 65,
+        B = 66,
+        C = 67,
+        D = 68,
+        E = 69,
+        F = 70,
+        G = 71,
+        H = 72,
+        I = 73,
+        J = 74,
+        K = 75,
+        L = 76,
+        M = 77,
+        N = 78,
+        O = 79,
+        P = 80,
+        Q = 81,
+        R = 82,
+        S = 83,
+        T = 84,
+        U = 85,
+        V = 86,
+        W = 87,
+        X = 88,
+        Y = 89,
+        Z = 90,
+
+        LeftBracket = 91,  /* [ */
+        Backslash = 92,    /* \ */
+        RightBracket = 93, /* ] */
+        GraveAccent = 96,  /* ` */
+
+        World1 = 161, /* non-US #1 */
+        /// Never produced, winit 0.27 has no key code for it. Kept so config
+        /// files that name it still load.
+        World2 = 162, /* non-US #2 */
+
+        /* Function keys */
+        Escape = 256,
+        Enter = 257,
+        Tab = 258,
+        Backspace = 259,
+        Insert = 260,
+        Delete = 261,
+        Right = 262,
+        Left = 263,
+        Down = 264,
+        Up = 265,
+        PageUp = 266,
+        PageDown = 267,
+        Home = 268,
+        End = 269,
+        CapsLock = 280,
+        ScrollLock = 281,
+        NumLock = 282,
+        PrintScreen = 283,
+        Pause = 284,
+        F1 = 290,
+        F2 = 291,
+        F3 = 292,
+        F4 = 293,
+        F5 = 294,
+        F6 = 295,
+        F7 = 296,
+        F8 = 297,
+        F9 = 298,
+        F10 = 299,
+        F11 = 300,
+        F12 = 301,
+        F13 = 302,
+        F14 = 303,
+        F15 = 304,
+        F16 = 305,
+        F17 = 306,
+        F18 = 307,
+        F19 = 308,
+        F20 = 309,
+        F21 = 310,
+        F22 = 311,
+        F23 = 312,
+        F24 = 313,
+        /// Never produced, winit 0.27 stops at F24. Kept so config files that
+        /// name it still load.
+        F25 = 314,
+
+        /* Keypad */
+        KP0 = 320,
+        KP1 = 321,
+        KP2 = 322,
+        KP3 = 323,
+        KP4 = 324,
+        KP5 = 325,
+        KP6 = 326,
+        KP7 = 327,
+        KP8 = 328,
+        KP9 = 329,
+        KPDecimal = 330,
+        KPDivide = 331,
+        KPMultiply = 332,
+        KPSubtract = 333,
+        KPAdd = 334,
+        KPEnter = 335,
+        KPEqual = 336,
+
+        LeftShift = 340,
+        LeftControl = 341,
+        LeftAlt = 342,
+        LeftSuper = 343,
+        RightShift = 344,
+        RightControl = 345,
+        RightAlt = 346,
+        RightSuper = 347,
+        Menu = 348,
+
+        /* Media keys */
+        MediaPlayPause = 400,
+        MediaStop = 401,
+        MediaNextTrack = 402,
+        MediaPrevTrack = 403,
+        MediaSelect = 404,
+        VolumeMute = 405,
+        VolumeDown = 406,
+        VolumeUp = 407,
+
+        /* Application and browser keys */
+        Calculator = 420,
+        Mail = 421,
+        MyComputer = 422,
+        WebBack = 423,
+        WebForward = 424,
+        WebHome = 425,
+        WebRefresh = 426,
+        WebSearch = 427,
+        WebStop = 428,
+        WebFavorites = 429,
+        NavigateBackward = 430,
+        NavigateForward = 431,
+        Copy = 432,
+        Cut = 433,
+        Paste = 434,
+        Stop = 435,
+
+        /* System keys */
+        Power = 440,
+        Sleep = 441,
+        Wake = 442,
+        SysRq = 443,
+
+        /* Keys of other layouts */
+        Asterisk = 460,  /* * */
+        At = 461,        /* @ */
+        Caret = 462,     /* ^ */
+        Colon = 463,     /* : */
+        Plus = 464,      /* + */
+        Underline = 465, /* _ */
+        Yen = 466,       /* ¥ */
+        AbntC1 = 467,    /* Brazilian / */
+        AbntC2 = 468,    /* Brazilian keypad . */
+        Ax = 469,        /* Japanese AX */
+        Kana = 470,
+        Kanji = 471,
+        Convert = 472,
+        NoConvert = 473,
+        Compose = 474,
+        KPComma = 475,
+        Unlabeled = 476,
+    }
+    /// A key the platform has no name for, by its scancode.
+    Unknown(u32)
 }
 
 impl KeyCode {
+    /// The key of a keyboard event. Keys without a name, which winit reports
+    /// without a virtual key code, are `Unknown` with their scancode.
+    pub fn from_input(input: &KeyboardInput) -> KeyCode {
+        input
+            .virtual_keycode
+            .map_or(KeyCode::Unknown(input.scancode), KeyCode::convert)
+    }
+
     pub fn convert(from: VirtualKeyCode) -> KeyCode {
         match from {
             Space => KeyCode::Space,
@@ -248,8 +436,111 @@ impl KeyCode {
             RShift => KeyCode::RightShift,
             RControl => KeyCode::RightControl,
             RAlt => KeyCode::RightAlt,
-            // dont care
-            _ => KeyCode::KPDivide
+            LWin => KeyCode::LeftSuper,
+            RWin => KeyCode::RightSuper,
+            Apps => KeyCode::Menu,
+            Capital => KeyCode::CapsLock,
+            Scroll => KeyCode::ScrollLock,
+            Snapshot => KeyCode::PrintScreen,
+            OEM102 => KeyCode::World1,
+            PlayPause => KeyCode::MediaPlayPause,
+            MediaStop => KeyCode::MediaStop,
+            NextTrack => KeyCode::MediaNextTrack,
+            PrevTrack => KeyCode::MediaPrevTrack,
+            MediaSelect => KeyCode::MediaSelect,
+            Mute => KeyCode::VolumeMute,
+            VolumeDown => KeyCode::VolumeDown,
+            VolumeUp => KeyCode::VolumeUp,
+            Calculator => KeyCode::Calculator,
+            Mail => KeyCode::Mail,
+            MyComputer => KeyCode::MyComputer,
+            WebBack => KeyCode::WebBack,
+            WebForward => KeyCode::WebForward,
+            WebHome => KeyCode::WebHome,
+            WebRefresh => KeyCode::WebRefresh,
+            WebSearch => KeyCode::WebSearch,
+            WebStop => KeyCode::WebStop,
+            WebFavorites => KeyCode::WebFavorites,
+            NavigateBackward => KeyCode::NavigateBackward,
+            NavigateForward => KeyCode::NavigateForward,
+            Copy => KeyCode::Copy,
+            Cut => KeyCode::Cut,
+            Paste => KeyCode::Paste,
+            Stop => KeyCode::Stop,
+            Power => KeyCode::Power,
+            Sleep => KeyCode::Sleep,
+            Wake => KeyCode::Wake,
+            Sysrq => KeyCode::SysRq,
+            Asterisk => KeyCode::Asterisk,
+            At => KeyCode::At,
+            Caret => KeyCode::Caret,
+            Colon => KeyCode::Colon,
+            Plus => KeyCode::Plus,
+            Underline => KeyCode::Underline,
+            Yen => KeyCode::Yen,
+            AbntC1 => KeyCode::AbntC1,
+            AbntC2 => KeyCode::AbntC2,
+            Ax => KeyCode::Ax,
+            Kana => KeyCode::Kana,
+            Kanji => KeyCode::Kanji,
+            Convert => KeyCode::Convert,
+            NoConvert => KeyCode::NoConvert,
+            Compose => KeyCode::Compose,
+            NumpadComma => KeyCode::KPComma,
+            Unlabeled => KeyCode::Unlabeled,
         }
     }
 }
+
+/// Modifier keys held down. Either key of a pair counts, like left or right
+/// shift.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers(0);
+    pub const SHIFT: Modifiers = Modifiers(1 << 0);
+    pub const CTRL: Modifiers = Modifiers(1 << 1);
+    pub const ALT: Modifiers = Modifiers(1 << 2);
+    /// The Windows key, or command on macOS.
+    pub const SUPER: Modifiers = Modifiers(1 << 3);
+
+    pub const fn union(self, other: Modifiers) -> Modifiers {
+        Modifiers(self.0 | other.0)
+    }
+
+    /// Whether all modifiers in `other` are held. Compare with `==` to also
+    /// require that no others are.
+    pub fn contains(self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn convert(from: ModifiersState) -> Modifiers {
+        let mut modifiers = Modifiers::NONE;
+        if from.shift() {
+            modifiers = modifiers | Modifiers::SHIFT;
+        }
+        if from.ctrl() {
+            modifiers = modifiers | Modifiers::CTRL;
+        }
+        if from.alt() {
+            modifiers = modifiers | Modifiers::ALT;
+        }
+        if from.logo() {
+            modifiers = modifiers | Modifiers::SUPER;
+        }
+        modifiers
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, rhs: Modifiers) -> Modifiers {
+        self.union(rhs)
+    }
+}
@@ -1,24 +1,29 @@
-use glium::{
-    glutin::{self, event_loop::ControlFlow},
-    Display, Frame,
-};
+use glium::Frame;
 
-use super::input::Input;
+use super::{event::Event, input::Input, timestep::Timestep};
 
 pub trait Layer {
     fn on_attach(&mut self) {}
     fn on_detach(&mut self) {}
-    fn on_update(&mut self, _target: &mut Frame, _input: &Input) {}
-    fn on_event(&mut self, _event: &mut Option<super::event::Event>) {}
+    /// Called at a fixed rate, zero or more times before each `on_update`.
+    /// Put simulation here so it does not depend on the frame rate.
+    fn on_fixed_update(&mut self, _input: &Input, _timestep: Timestep) {}
+    fn on_update(&mut self, _target: &mut Frame, _input: &Input, _timestep: Timestep) {}
+    /// Called for every event, from the top of the stack down. Return `true`
+    /// when the event is handled, so it doesn't reach the layers below.
+    fn on_event(&mut self, _event: &Event) -> bool {
+        false
+    }
+    /// Builds this layer's ui. All layers share one egui pass per frame, drawn
+    /// on top of everything `on_update` drew. Use `painter` to show textures
+    /// the engine rendered, like a `Framebuffer`.
     fn on_egui_render(
         &mut self,
+        _egui_ctx: &egui::Context,
+        _painter: &mut egui_glium::Painter,
         _input: &Input,
-        _display: &Display,
-        _target: &mut Frame,
-        _event: &glutin::event::Event<()>,
-        _control_flow: &mut ControlFlow,
-        _egui_glium: &mut egui_glium::EguiGlium,
-    ) {}
+    ) {
+    }
 }
 
 pub struct LayerStack {
@@ -26,8 +31,14 @@ pub struct LayerStack {
     first_overlay_index: usize,
 }
 
+impl Default for LayerStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 fn cmp<T>(a1: &T, a2: &T) -> bool {
-    a1 as *const _ == a2 as *const _
+    std::ptr::eq(a1, a2)
 }
 
 impl LayerStack {
@@ -66,7 +77,52 @@ impl LayerStack {
         }
     }
 
-    pub fn iter_mut(&mut self) -> std::slice::IterMut<Box<dyn Layer>> {
+    /// Sends `event` to the overlays first, then down to the bottom layer,
+    /// until a layer handles it. Returns whether one did.
+    pub fn on_event(&mut self, event: &Event) -> bool {
+        self.layers
+            .iter_mut()
+            .rev()
+            .any(|layer| layer.on_event(event))
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Box<dyn Layer>> {
         self.layers.iter_mut()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::sequoia::{event, keycode::KeyCode, physical_key::PhysicalKey};
+
+    /// Records its name when it sees an event, and handles key presses.
+    struct NamedLayer(&'static str, Rc<RefCell<Vec<&'static str>>>);
+
+    impl Layer for NamedLayer {
+        fn on_event(&mut self, event: &Event) -> bool {
+            self.1.borrow_mut().push(self.0);
+            let mut dispatcher = event::EventDispatcher::new(event);
+            dispatcher.dispatch::<event::KeyPress>(|_| true);
+            dispatcher.handled()
+        }
+    }
+
+    #[test]
+    fn events_go_down_the_stack_until_handled() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut stack = LayerStack::new();
+        stack.push_overlay(Box::new(NamedLayer("overlay", seen.clone())));
+        stack.push_layer(Box::new(NamedLayer("bottom", seen.clone())));
+        stack.push_layer(Box::new(NamedLayer("top", seen.clone())));
+
+        assert!(!stack.on_event(&event::MouseMove { x: 1.0, y: 2.0 }.into()));
+        assert_eq!(*seen.borrow(), ["overlay", "top", "bottom"]);
+
+        seen.borrow_mut().clear();
+        assert!(stack.on_event(&event::KeyPress::new(KeyCode::A, PhysicalKey::A).into()));
+        assert_eq!(*seen.borrow(), ["overlay"]);
+    }
+}
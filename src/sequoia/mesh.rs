@@ -0,0 +1,105 @@
+use std::fmt;
+
+use glium::backend::Facade;
+
+use super::{
+    buffer::{BufferElement, BufferError, BufferLayout, ShaderDataType, VertexBuffer},
+    renderer::VertexArray,
+};
+
+#[derive(Debug)]
+pub enum MeshError {
+    VertexBuffer(BufferError),
+    IndexBuffer(glium::index::BufferCreationError),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::VertexBuffer(why) => write!(f, "{}", why),
+            MeshError::IndexBuffer(why) => write!(f, "could not create index buffer: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+impl From<BufferError> for MeshError {
+    fn from(why: BufferError) -> Self {
+        MeshError::VertexBuffer(why)
+    }
+}
+
+impl From<glium::index::BufferCreationError> for MeshError {
+    fn from(why: glium::index::BufferCreationError) -> Self {
+        MeshError::IndexBuffer(why)
+    }
+}
+
+/// Triangles with any vertex layout, drawn with `Renderer::draw_mesh`.
+pub struct Mesh {
+    vertex_array: VertexArray,
+}
+
+impl Mesh {
+    pub fn new<T: Copy + Send + 'static>(
+        display: &impl Facade,
+        layout: BufferLayout,
+        vertices: &[T],
+        indices: &[u32],
+    ) -> Result<Self, MeshError> {
+        let vertex_buffer = VertexBuffer::new(display, layout, vertices)?;
+        let index_buffer =
+            glium::IndexBuffer::new(display, glium::index::PrimitiveType::TrianglesList, indices)?;
+        Ok(Self {
+            vertex_array: VertexArray {
+                vertex_buffers: vec![vertex_buffer],
+                index_buffer,
+            },
+        })
+    }
+
+    /// A unit cube centered on the origin, with a `position` and a `normal`
+    /// per vertex.
+    pub fn cube(display: &impl Facade) -> Result<Self, MeshError> {
+        let layout = BufferLayout::new(vec![
+            BufferElement::new(ShaderDataType::Float3, "position"),
+            BufferElement::new(ShaderDataType::Float3, "normal"),
+        ]);
+
+        // every face needs its own vertices, since the normals differ
+        let faces: [(glam::Vec3, glam::Vec3, glam::Vec3); 6] = [
+            (glam::Vec3::X, glam::Vec3::Y, glam::Vec3::Z),
+            (-glam::Vec3::X, glam::Vec3::Y, -glam::Vec3::Z),
+            (glam::Vec3::Y, glam::Vec3::Z, glam::Vec3::X),
+            (-glam::Vec3::Y, glam::Vec3::Z, -glam::Vec3::X),
+            (glam::Vec3::Z, glam::Vec3::X, glam::Vec3::Y),
+            (-glam::Vec3::Z, glam::Vec3::X, -glam::Vec3::Y),
+        ];
+        let mut vertices: Vec<[f32; 6]> = Vec::with_capacity(6 * 4);
+        let mut indices: Vec<u32> = Vec::with_capacity(6 * 6);
+        for (normal, u, v) in faces {
+            let offset = vertices.len() as u32;
+            for (du, dv) in [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)] {
+                let position = normal * 0.5 + u * du + v * dv;
+                vertices.push([
+                    position.x, position.y, position.z, normal.x, normal.y, normal.z,
+                ]);
+            }
+            indices.extend([
+                offset,
+                offset + 1,
+                offset + 2,
+                offset + 2,
+                offset + 3,
+                offset,
+            ]);
+        }
+
+        Self::new(display, layout, &vertices, &indices)
+    }
+
+    pub fn vertex_array(&self) -> &VertexArray {
+        &self.vertex_array
+    }
+}
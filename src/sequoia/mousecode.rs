@@ -1,19 +1,21 @@
 use glium::glutin::event::MouseButton;
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
-pub enum MouseCode {
-    Button0 = 0,
-    Button1 = 1,
-    Button2 = 2,
-    Button3 = 3,
-    Button4 = 4,
-    Button5 = 5,
-    Button6 = 6,
-    Button7 = 7,
-    // ButtonLast             = Button7,
-    // ButtonLeft             = Button0,
-    // ButtonRight            = Button1,
-    // ButtonMiddle           = Button2
+named_keys! {
+    #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+    pub enum MouseCode {
+        Button0 = 0,
+        Button1 = 1,
+        Button2 = 2,
+        Button3 = 3,
+        Button4 = 4,
+        Button5 = 5,
+        Button6 = 6,
+        Button7 = 7,
+        // ButtonLast             = Button7,
+        // ButtonLeft             = Button0,
+        // ButtonRight            = Button1,
+        // ButtonMiddle           = Button2
+    }
 }
 
 impl MouseCode {
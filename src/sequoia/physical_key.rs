@@ -0,0 +1,439 @@
+use glium::glutin::event::ScanCode;
+
+named_keys! {
+    /// A key by where it is on the keyboard, whatever the layout. Variants
+    /// are named after the key in that place on a US QWERTY keyboard, so `W`
+    /// is the key above `S`, which is labeled Z on a French AZERTY keyboard.
+    /// Bind movement to these, and show the `KeyCode` of the same key as its
+    /// label.
+    #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+    pub enum PhysicalKey {
+        Escape,
+        D1,
+        D2,
+        D3,
+        D4,
+        D5,
+        D6,
+        D7,
+        D8,
+        D9,
+        D0,
+        Minus,
+        Equal,
+        Backspace,
+        Tab,
+        Q,
+        W,
+        E,
+        R,
+        T,
+        Y,
+        U,
+        I,
+        O,
+        P,
+        LeftBracket,
+        RightBracket,
+        Enter,
+        A,
+        S,
+        D,
+        F,
+        G,
+        H,
+        J,
+        K,
+        L,
+        Semicolon,
+        Apostrophe,
+        GraveAccent,
+        Backslash,
+        /// The extra key next to left shift on ISO keyboards.
+        World1,
+        Z,
+        X,
+        C,
+        V,
+        B,
+        N,
+        M,
+        Comma,
+        Period,
+        Slash,
+        Space,
+        CapsLock,
+
+        F1,
+        F2,
+        F3,
+        F4,
+        F5,
+        F6,
+        F7,
+        F8,
+        F9,
+        F10,
+        F11,
+        F12,
+
+        PrintScreen,
+        ScrollLock,
+        Pause,
+        Insert,
+        Delete,
+        Home,
+        End,
+        PageUp,
+        PageDown,
+        Right,
+        Left,
+        Down,
+        Up,
+
+        NumLock,
+        KP0,
+        KP1,
+        KP2,
+        KP3,
+        KP4,
+        KP5,
+        KP6,
+        KP7,
+        KP8,
+        KP9,
+        KPDecimal,
+        KPDivide,
+        KPMultiply,
+        KPSubtract,
+        KPAdd,
+        KPEnter,
+        KPEqual,
+
+        LeftShift,
+        LeftControl,
+        LeftAlt,
+        LeftSuper,
+        RightShift,
+        RightControl,
+        RightAlt,
+        RightSuper,
+        Menu,
+    }
+    /// A key without a name here, by its platform-specific scancode.
+    Unknown(ScanCode)
+}
+
+impl PhysicalKey {
+    /// The key with `scancode`, as winit reports it on this platform: a PC
+    /// set 1 scancode on Windows, a Linux evdev code on Linux and the BSDs,
+    /// and a virtual key code on macOS.
+    pub fn from_scancode(scancode: ScanCode) -> PhysicalKey {
+        use PhysicalKey::*;
+
+        #[cfg(target_os = "macos")]
+        let key = match scancode {
+            0 => A,
+            1 => S,
+            2 => D,
+            3 => F,
+            4 => H,
+            5 => G,
+            6 => Z,
+            7 => X,
+            8 => C,
+            9 => V,
+            10 => World1,
+            11 => B,
+            12 => Q,
+            13 => W,
+            14 => E,
+            15 => R,
+            16 => Y,
+            17 => T,
+            18 => D1,
+            19 => D2,
+            20 => D3,
+            21 => D4,
+            22 => D6,
+            23 => D5,
+            24 => Equal,
+            25 => D9,
+            26 => D7,
+            27 => Minus,
+            28 => D8,
+            29 => D0,
+            30 => RightBracket,
+            31 => O,
+            32 => U,
+            33 => LeftBracket,
+            34 => I,
+            35 => P,
+            36 => Enter,
+            37 => L,
+            38 => J,
+            39 => Apostrophe,
+            40 => K,
+            41 => Semicolon,
+            42 => Backslash,
+            43 => Comma,
+            44 => Slash,
+            45 => N,
+            46 => M,
+            47 => Period,
+            48 => Tab,
+            49 => Space,
+            50 => GraveAccent,
+            51 => Backspace,
+            53 => Escape,
+            54 => RightSuper,
+            55 => LeftSuper,
+            56 => LeftShift,
+            57 => CapsLock,
+            58 => LeftAlt,
+            59 => LeftControl,
+            60 => RightShift,
+            61 => RightAlt,
+            62 => RightControl,
+            65 => KPDecimal,
+            67 => KPMultiply,
+            69 => KPAdd,
+            71 => NumLock,
+            75 => KPDivide,
+            76 => KPEnter,
+            78 => KPSubtract,
+            81 => KPEqual,
+            82 => KP0,
+            83 => KP1,
+            84 => KP2,
+            85 => KP3,
+            86 => KP4,
+            87 => KP5,
+            88 => KP6,
+            89 => KP7,
+            91 => KP8,
+            92 => KP9,
+            96 => F5,
+            97 => F6,
+            98 => F7,
+            99 => F3,
+            100 => F8,
+            101 => F9,
+            103 => F11,
+            109 => F10,
+            110 => Menu,
+            111 => F12,
+            114 => Insert,
+            115 => Home,
+            116 => PageUp,
+            117 => Delete,
+            118 => F4,
+            119 => End,
+            120 => F2,
+            121 => PageDown,
+            122 => F1,
+            123 => Left,
+            124 => Right,
+            125 => Down,
+            126 => Up,
+            _ => Unknown(scancode),
+        };
+
+        // the main block is the same in set 1 and evdev, the keys that
+        // came later differ
+        #[cfg(not(target_os = "macos"))]
+        let key = match scancode {
+            1 => Escape,
+            2 => D1,
+            3 => D2,
+            4 => D3,
+            5 => D4,
+            6 => D5,
+            7 => D6,
+            8 => D7,
+            9 => D8,
+            10 => D9,
+            11 => D0,
+            12 => Minus,
+            13 => Equal,
+            14 => Backspace,
+            15 => Tab,
+            16 => Q,
+            17 => W,
+            18 => E,
+            19 => R,
+            20 => T,
+            21 => Y,
+            22 => U,
+            23 => I,
+            24 => O,
+            25 => P,
+            26 => LeftBracket,
+            27 => RightBracket,
+            28 => Enter,
+            29 => LeftControl,
+            30 => A,
+            31 => S,
+            32 => D,
+            33 => F,
+            34 => G,
+            35 => H,
+            36 => J,
+            37 => K,
+            38 => L,
+            39 => Semicolon,
+            40 => Apostrophe,
+            41 => GraveAccent,
+            42 => LeftShift,
+            43 => Backslash,
+            44 => Z,
+            45 => X,
+            46 => C,
+            47 => V,
+            48 => B,
+            49 => N,
+            50 => M,
+            51 => Comma,
+            52 => Period,
+            53 => Slash,
+            54 => RightShift,
+            55 => KPMultiply,
+            56 => LeftAlt,
+            57 => Space,
+            58 => CapsLock,
+            59 => F1,
+            60 => F2,
+            61 => F3,
+            62 => F4,
+            63 => F5,
+            64 => F6,
+            65 => F7,
+            66 => F8,
+            67 => F9,
+            68 => F10,
+            69 => NumLock,
+            70 => ScrollLock,
+            71 => KP7,
+            72 => KP8,
+            73 => KP9,
+            74 => KPSubtract,
+            75 => KP4,
+            76 => KP5,
+            77 => KP6,
+            78 => KPAdd,
+            79 => KP1,
+            80 => KP2,
+            81 => KP3,
+            82 => KP0,
+            83 => KPDecimal,
+            86 => World1,
+            87 => F11,
+            88 => F12,
+
+            // windows marks the extended keys with 0xE000
+            #[cfg(target_os = "windows")]
+            0xE01C => KPEnter,
+            #[cfg(target_os = "windows")]
+            0xE01D => RightControl,
+            #[cfg(target_os = "windows")]
+            0xE035 => KPDivide,
+            #[cfg(target_os = "windows")]
+            0xE037 => PrintScreen,
+            #[cfg(target_os = "windows")]
+            0xE038 => RightAlt,
+            #[cfg(target_os = "windows")]
+            0xE045 => NumLock,
+            #[cfg(target_os = "windows")]
+            0xE047 => Home,
+            #[cfg(target_os = "windows")]
+            0xE048 => Up,
+            #[cfg(target_os = "windows")]
+            0xE049 => PageUp,
+            #[cfg(target_os = "windows")]
+            0xE04B => Left,
+            #[cfg(target_os = "windows")]
+            0xE04D => Right,
+            #[cfg(target_os = "windows")]
+            0xE04F => End,
+            #[cfg(target_os = "windows")]
+            0xE050 => Down,
+            #[cfg(target_os = "windows")]
+            0xE051 => PageDown,
+            #[cfg(target_os = "windows")]
+            0xE052 => Insert,
+            #[cfg(target_os = "windows")]
+            0xE053 => Delete,
+            #[cfg(target_os = "windows")]
+            0xE059 => Pause,
+            #[cfg(target_os = "windows")]
+            0xE05B => LeftSuper,
+            #[cfg(target_os = "windows")]
+            0xE05C => RightSuper,
+            #[cfg(target_os = "windows")]
+            0xE05D => Menu,
+
+            #[cfg(not(target_os = "windows"))]
+            96 => KPEnter,
+            #[cfg(not(target_os = "windows"))]
+            97 => RightControl,
+            #[cfg(not(target_os = "windows"))]
+            98 => KPDivide,
+            #[cfg(not(target_os = "windows"))]
+            99 => PrintScreen,
+            #[cfg(not(target_os = "windows"))]
+            100 => RightAlt,
+            #[cfg(not(target_os = "windows"))]
+            102 => Home,
+            #[cfg(not(target_os = "windows"))]
+            103 => Up,
+            #[cfg(not(target_os = "windows"))]
+            104 => PageUp,
+            #[cfg(not(target_os = "windows"))]
+            105 => Left,
+            #[cfg(not(target_os = "windows"))]
+            106 => Right,
+            #[cfg(not(target_os = "windows"))]
+            107 => End,
+            #[cfg(not(target_os = "windows"))]
+            108 => Down,
+            #[cfg(not(target_os = "windows"))]
+            109 => PageDown,
+            #[cfg(not(target_os = "windows"))]
+            110 => Insert,
+            #[cfg(not(target_os = "windows"))]
+            111 => Delete,
+            #[cfg(not(target_os = "windows"))]
+            117 => KPEqual,
+            #[cfg(not(target_os = "windows"))]
+            119 => Pause,
+            #[cfg(not(target_os = "windows"))]
+            125 => LeftSuper,
+            #[cfg(not(target_os = "windows"))]
+            126 => RightSuper,
+            #[cfg(not(target_os = "windows"))]
+            127 => Menu,
+
+            _ => Unknown(scancode),
+        };
+
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    #[test]
+    fn scancodes_name_positions() {
+        // the same on both
+        assert_eq!(PhysicalKey::from_scancode(17), PhysicalKey::W);
+        assert_eq!(PhysicalKey::from_scancode(30), PhysicalKey::A);
+        assert_eq!(PhysicalKey::from_scancode(57), PhysicalKey::Space);
+        assert_eq!(
+            PhysicalKey::from_scancode(0x1234),
+            PhysicalKey::Unknown(0x1234)
+        );
+    }
+}
@@ -1,51 +1,293 @@
-use glium::Surface;
+use std::rc::Rc;
 
+use glium::{
+    backend::{Context, Facade},
+    uniforms::{UniformValue, Uniforms},
+    Surface,
+};
 
-#[derive(Copy, Clone)]
-pub struct Vertex {
-    position: [f32; 3],
-}
+use super::{
+    buffer::{VertexBuffer, VertexBuffers},
+    capture,
+    mesh::Mesh,
+    shader::Shader,
+};
 
-impl Vertex {
-    pub fn from(position: [f32; 3]) -> Self {
-        Self { position }
-    }
+/// Vertex buffers that together describe the vertices of a mesh, and the
+/// indices to draw them with.
+pub struct VertexArray {
+    pub vertex_buffers: Vec<VertexBuffer>,
+    pub index_buffer: glium::IndexBuffer<u32>,
 }
 
-glium::implement_vertex!(Vertex, position);
+/// The shader's own uniforms plus the ones the renderer provides.
+struct SceneUniforms<'a> {
+    view_projection_matrix: [[f32; 4]; 4],
+    transform: [[f32; 4]; 4],
+    shader: &'a Shader,
+}
 
-pub struct VertexArray {
-    pub vertex_buffers: Vec<glium::VertexBuffer<Vertex>>,
-    pub index_buffer: glium::IndexBuffer<u32>,
+impl Uniforms for SceneUniforms<'_> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut visit: F) {
+        visit(
+            "u_ViewProjection",
+            UniformValue::Mat4(self.view_projection_matrix),
+        );
+        visit("u_Transform", UniformValue::Mat4(self.transform));
+        self.shader.visit_values(visit);
+    }
 }
 
 pub struct Renderer;
 
 impl Renderer {
-    pub fn set_clear_color(target: &mut glium::Frame, color: glam::Vec4) {
-        target.clear_color(color.x, color.y, color.z, color.w)
+    pub fn set_clear_color<S: Surface>(target: &mut S, color: glam::Vec4) {
+        target.clear_color_and_depth((color.x, color.y, color.z, color.w), 1.0)
+    }
+    pub fn draw_indexed<S: Surface>(
+        target: &mut S,
+        shader: &Shader,
+        vertex_array: &VertexArray,
+        view_projection_matrix: glam::Mat4,
+    ) {
+        Self::draw_transformed(
+            target,
+            shader,
+            vertex_array,
+            view_projection_matrix,
+            glam::Mat4::IDENTITY,
+        );
     }
-    pub fn draw_indexed(
-        target: &mut glium::Frame,
-        program: &glium::Program,
+
+    /// Draws a mesh placed in the world by `transform`, which the shader
+    /// receives as `u_Transform`.
+    pub fn draw_mesh<S: Surface>(
+        target: &mut S,
+        shader: &Shader,
+        mesh: &Mesh,
+        view_projection_matrix: glam::Mat4,
+        transform: glam::Mat4,
+    ) {
+        Self::draw_transformed(
+            target,
+            shader,
+            mesh.vertex_array(),
+            view_projection_matrix,
+            transform,
+        );
+    }
+
+    fn draw_transformed<S: Surface>(
+        target: &mut S,
+        shader: &Shader,
         vertex_array: &VertexArray,
-        view_projection_matrix: glam::Mat4
+        view_projection_matrix: glam::Mat4,
+        transform: glam::Mat4,
     ) {
-        for vertex_buffer in vertex_array.vertex_buffers.iter() {
-            let uniforms = glium::uniform! { u_ViewProjection: view_projection_matrix.to_cols_array_2d() };
-            target
-                .draw(
-                    vertex_buffer,
-                    &vertex_array.index_buffer,
-                    program,
-                    &uniforms,
-                    &Default::default(),
-                )
-                .unwrap();
+        let uniforms = SceneUniforms {
+            view_projection_matrix: view_projection_matrix.to_cols_array_2d(),
+            transform: transform.to_cols_array_2d(),
+            shader,
+        };
+        let draw_parameters = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::IfLess,
+                write: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        target
+            .draw(
+                VertexBuffers(&vertex_array.vertex_buffers),
+                &vertex_array.index_buffer,
+                shader.program(),
+                &uniforms,
+                &draw_parameters,
+            )
+            .unwrap();
+    }
+}
+
+/// An offscreen render target backed by a texture, so the scene can be shown
+/// inside an egui widget instead of being drawn straight to the window.
+pub struct Framebuffer {
+    context: Rc<Context>,
+    color: Rc<glium::texture::SrgbTexture2d>,
+    depth: glium::framebuffer::DepthRenderBuffer,
+    width: u32,
+    height: u32,
+    texture_id: Option<egui::TextureId>,
+}
+
+impl Framebuffer {
+    pub fn new(display: &impl Facade, width: u32, height: u32) -> Self {
+        let (color, depth) = Self::create_attachments(display, width, height);
+        Self {
+            context: display.get_context().clone(),
+            color,
+            depth,
+            width,
+            height,
+            texture_id: None,
         }
     }
+
+    fn create_attachments(
+        display: &impl Facade,
+        width: u32,
+        height: u32,
+    ) -> (
+        Rc<glium::texture::SrgbTexture2d>,
+        glium::framebuffer::DepthRenderBuffer,
+    ) {
+        // a zero sized texture is invalid, which happens while the tab is collapsed
+        let width = width.max(1);
+        let height = height.max(1);
+        let color = glium::texture::SrgbTexture2d::empty_with_format(
+            display,
+            glium::texture::SrgbFormat::U8U8U8U8,
+            glium::texture::MipmapsOption::NoMipmap,
+            width,
+            height,
+        )
+        .unwrap();
+        let depth = glium::framebuffer::DepthRenderBuffer::new(
+            display,
+            glium::texture::DepthFormat::I24,
+            width,
+            height,
+        )
+        .unwrap();
+        (Rc::new(color), depth)
+    }
+
+    /// Recreates the attachments if the size changed.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == self.width && height == self.height {
+            return;
+        }
+        let (color, depth) = Self::create_attachments(&self.context, width, height);
+        self.color = color;
+        self.depth = depth;
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// A surface to pass to the `Renderer` functions.
+    pub fn surface(&self) -> glium::framebuffer::SimpleFrameBuffer<'_> {
+        glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(
+            &self.context,
+            &*self.color,
+            &self.depth,
+        )
+        .unwrap()
+    }
+
+    /// Reads the color attachment back from the GPU, top row first.
+    pub fn read_pixels(&self) -> image::RgbaImage {
+        capture::into_image(self.color.read())
+    }
+
+    /// Registers the color attachment as an egui user texture, so it can be
+    /// displayed with an `egui::Image`. Call once per frame, since resizing
+    /// replaces the underlying texture.
+    pub fn texture_id(&mut self, painter: &mut egui_glium::Painter) -> egui::TextureId {
+        let options = egui::TextureOptions::LINEAR;
+        match self.texture_id {
+            Some(id) => {
+                painter.replace_native_texture(id, self.color.clone(), options);
+                id
+            }
+            None => {
+                let id = painter.register_native_texture(self.color.clone(), options);
+                self.texture_id = Some(id);
+                id
+            }
+        }
+    }
+}
+
+/// The part of the window the scene is presented in, in egui points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub rect: egui::Rect,
+    pub pixels_per_point: f32,
+}
+
+impl Viewport {
+    /// Size of the viewport in physical pixels, used to size the `Framebuffer`.
+    pub fn size_in_pixels(&self) -> (u32, u32) {
+        let size = self.rect.size() * self.pixels_per_point;
+        (size.x.round() as u32, size.y.round() as u32)
+    }
+
+    /// Maps a position in window pixels (like `Input::mouse_location`) to
+    /// pixels relative to the top left of the viewport. Returns `None` if the
+    /// position is outside of the viewport.
+    pub fn local_position(&self, (x, y): (f64, f64)) -> Option<(f64, f64)> {
+        let point = egui::pos2(
+            x as f32 / self.pixels_per_point,
+            y as f32 / self.pixels_per_point,
+        );
+        if !self.rect.contains(point) {
+            return None;
+        }
+        let local = (point - self.rect.min) * self.pixels_per_point;
+        Some((local.x as f64, local.y as f64))
+    }
+
+    /// Maps a position in window pixels to normalized device coordinates of
+    /// the viewport, where (-1, -1) is the bottom left and (1, 1) the top right.
+    /// Positions outside of the viewport map outside of that range.
+    pub fn window_to_ndc(&self, (x, y): (f64, f64)) -> glam::Vec2 {
+        let local = egui::pos2(
+            x as f32 / self.pixels_per_point,
+            y as f32 / self.pixels_per_point,
+        ) - self.rect.min;
+        glam::vec2(
+            local.x / self.rect.width() * 2.0 - 1.0,
+            1.0 - local.y / self.rect.height() * 2.0,
+        )
+    }
+
+    /// The inverse of `window_to_ndc`.
+    pub fn ndc_to_window(&self, ndc: glam::Vec2) -> (f64, f64) {
+        let local = egui::vec2(
+            (ndc.x + 1.0) / 2.0 * self.rect.width(),
+            (1.0 - ndc.y) / 2.0 * self.rect.height(),
+        );
+        let window = (self.rect.min + local).to_vec2() * self.pixels_per_point;
+        (window.x as f64, window.y as f64)
+    }
+}
+
+/// Anything that can be used to look at a scene.
+pub trait Camera {
+    fn view_projection_matrix(&self) -> glam::Mat4;
+
+    /// Converts a position in window pixels, like `Input::mouse_location`, to
+    /// the point in the world under it, on the near plane of the camera. For
+    /// an `OrthographicCamera` the x and y do not depend on the depth.
+    fn screen_to_world(&self, point: (f64, f64), viewport: &Viewport) -> glam::Vec3 {
+        let ndc = viewport.window_to_ndc(point);
+        self.view_projection_matrix()
+            .inverse()
+            .project_point3(ndc.extend(0.0))
+    }
+
+    /// Converts a point in the world to window pixels.
+    fn world_to_screen(&self, point: glam::Vec3, viewport: &Viewport) -> (f64, f64) {
+        let ndc = self.view_projection_matrix().project_point3(point);
+        viewport.ndc_to_window(ndc.truncate())
+    }
 }
 
+/// A 2D camera. All angles are in radians, counter clockwise, like in `glam`.
 pub struct OrthographicCamera {
     projection_matrix: glam::Mat4,
     view_matrix: glam::Mat4,
@@ -55,35 +297,53 @@ pub struct OrthographicCamera {
 }
 
 impl OrthographicCamera {
-    pub fn new(left: f32, right: f32, bottom: f32, top: f32, position: glam::Vec3, rotation: f32) -> Self {
-        let projection_matrix = glam::Mat4::orthographic_lh(left, right, bottom, top, -1.0, 1.0);
-
-        let transform = glam::Mat4::from_translation(position)
-            * glam::Mat4::from_rotation_z(rotation);
-        let view_matrix = transform.inverse();
-        let view_projection_matrix = projection_matrix * view_matrix;
-        Self {
-            projection_matrix,
-            view_matrix,
-            view_projection_matrix,
+    pub fn new(
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        position: glam::Vec3,
+        rotation: f32,
+    ) -> Self {
+        let mut camera = Self {
+            projection_matrix: glam::Mat4::orthographic_lh(left, right, bottom, top, -1.0, 1.0),
+            view_matrix: glam::Mat4::IDENTITY,
+            view_projection_matrix: glam::Mat4::IDENTITY,
             position,
             rotation,
-        }
+        };
+        camera.recalculate_view_matrix();
+        camera
+    }
+
+    pub fn set_projection(&mut self, left: f32, right: f32, bottom: f32, top: f32) {
+        self.projection_matrix = glam::Mat4::orthographic_lh(left, right, bottom, top, -1.0, 1.0);
+        self.view_projection_matrix = self.projection_matrix * self.view_matrix;
+    }
+
+    pub fn position(&self) -> glam::Vec3 {
+        self.position
     }
 
     pub fn set_position(&mut self, position: glam::Vec3) {
         self.position = position;
         self.recalculate_view_matrix();
     }
+
+    /// Rotation around the z axis, in radians.
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// Sets the rotation around the z axis, in radians.
     pub fn set_rotation(&mut self, rotation: f32) {
         self.rotation = rotation;
         self.recalculate_view_matrix();
     }
 
-
     fn recalculate_view_matrix(&mut self) {
         let transform = glam::Mat4::from_translation(self.position)
-            * glam::Mat4::from_rotation_z(self.rotation.to_radians());
+            * glam::Mat4::from_rotation_z(self.rotation);
         self.view_matrix = transform.inverse();
         self.view_projection_matrix = self.projection_matrix * self.view_matrix;
     }
@@ -100,3 +360,434 @@ impl OrthographicCamera {
         self.projection_matrix
     }
 }
+
+impl Camera for OrthographicCamera {
+    fn view_projection_matrix(&self) -> glam::Mat4 {
+        self.view_projection_matrix
+    }
+}
+
+/// A 3D camera with a field of view. It looks down +z when yaw and pitch are
+/// zero; yaw turns it towards +x and pitch towards +y. Angles are in radians.
+pub struct PerspectiveCamera {
+    fov: f32,
+    aspect_ratio: f32,
+    near: f32,
+    far: f32,
+    position: glam::Vec3,
+    yaw: f32,
+    pitch: f32,
+    projection_matrix: glam::Mat4,
+    view_matrix: glam::Mat4,
+    view_projection_matrix: glam::Mat4,
+}
+
+impl PerspectiveCamera {
+    // looking straight up or down makes the up vector degenerate
+    const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+    /// `fov` is the vertical field of view.
+    pub fn new(fov: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        let mut camera = Self {
+            fov,
+            aspect_ratio,
+            near,
+            far,
+            position: glam::Vec3::ZERO,
+            yaw: 0.0,
+            pitch: 0.0,
+            projection_matrix: glam::Mat4::IDENTITY,
+            view_matrix: glam::Mat4::IDENTITY,
+            view_projection_matrix: glam::Mat4::IDENTITY,
+        };
+        camera.recalculate_projection_matrix();
+        camera.recalculate_view_matrix();
+        camera
+    }
+
+    pub fn set_projection(&mut self, fov: f32, aspect_ratio: f32, near: f32, far: f32) {
+        self.fov = fov;
+        self.aspect_ratio = aspect_ratio;
+        self.near = near;
+        self.far = far;
+        self.recalculate_projection_matrix();
+    }
+
+    pub fn fov(&self) -> f32 {
+        self.fov
+    }
+
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov;
+        self.recalculate_projection_matrix();
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+        self.aspect_ratio = aspect_ratio;
+        self.recalculate_projection_matrix();
+    }
+
+    pub fn position(&self) -> glam::Vec3 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: glam::Vec3) {
+        self.position = position;
+        self.recalculate_view_matrix();
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    /// Sets the orientation. Pitch is clamped to just short of straight up
+    /// or down.
+    pub fn set_rotation(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw % std::f32::consts::TAU;
+        self.pitch = pitch.clamp(-Self::MAX_PITCH, Self::MAX_PITCH);
+        self.recalculate_view_matrix();
+    }
+
+    pub fn forward(&self) -> glam::Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        glam::vec3(cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw)
+    }
+
+    pub fn right(&self) -> glam::Vec3 {
+        glam::Vec3::Y.cross(self.forward()).normalize()
+    }
+
+    pub fn up(&self) -> glam::Vec3 {
+        self.forward().cross(self.right())
+    }
+
+    fn recalculate_projection_matrix(&mut self) {
+        self.projection_matrix =
+            glam::Mat4::perspective_lh(self.fov, self.aspect_ratio, self.near, self.far);
+        self.view_projection_matrix = self.projection_matrix * self.view_matrix;
+    }
+
+    fn recalculate_view_matrix(&mut self) {
+        self.view_matrix =
+            glam::Mat4::look_at_lh(self.position, self.position + self.forward(), glam::Vec3::Y);
+        self.view_projection_matrix = self.projection_matrix * self.view_matrix;
+    }
+
+    pub fn view_projection_matrix(&self) -> glam::Mat4 {
+        self.view_projection_matrix
+    }
+
+    pub fn view_matrix(&self) -> glam::Mat4 {
+        self.view_matrix
+    }
+
+    pub fn projection_matrix(&self) -> glam::Mat4 {
+        self.projection_matrix
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn view_projection_matrix(&self) -> glam::Mat4 {
+        self.view_projection_matrix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_mat4_eq(a: glam::Mat4, b: glam::Mat4) {
+        assert!(a.abs_diff_eq(b, 1e-6), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn new_and_set_rotation_agree() {
+        let rotation = 45.0_f32.to_radians();
+        let constructed = OrthographicCamera::new(-1.6, 1.6, -0.9, 0.9, glam::Vec3::ZERO, rotation);
+        let mut set = OrthographicCamera::new(-1.6, 1.6, -0.9, 0.9, glam::Vec3::ZERO, 0.0);
+        set.set_rotation(rotation);
+
+        assert_mat4_eq(constructed.view_matrix(), set.view_matrix());
+        assert_mat4_eq(
+            constructed.view_projection_matrix(),
+            set.view_projection_matrix(),
+        );
+    }
+
+    #[test]
+    fn new_and_set_position_agree() {
+        let position = glam::vec3(0.5, -0.25, 0.0);
+        let constructed = OrthographicCamera::new(-1.6, 1.6, -0.9, 0.9, position, 0.0);
+        let mut set = OrthographicCamera::new(-1.6, 1.6, -0.9, 0.9, glam::Vec3::ZERO, 0.0);
+        set.set_position(position);
+
+        assert_mat4_eq(constructed.view_matrix(), set.view_matrix());
+        assert_mat4_eq(
+            constructed.view_projection_matrix(),
+            set.view_projection_matrix(),
+        );
+    }
+
+    #[test]
+    fn set_position_keeps_rotation() {
+        let rotation = 30.0_f32.to_radians();
+        let mut camera = OrthographicCamera::new(-1.6, 1.6, -0.9, 0.9, glam::Vec3::ZERO, rotation);
+        let before = camera.view_matrix();
+        camera.set_position(glam::Vec3::ZERO);
+
+        assert_mat4_eq(before, camera.view_matrix());
+        assert_eq!(camera.rotation(), rotation);
+    }
+
+    #[test]
+    fn rotation_is_in_radians() {
+        let camera = OrthographicCamera::new(
+            -1.0,
+            1.0,
+            -1.0,
+            1.0,
+            glam::Vec3::ZERO,
+            std::f32::consts::FRAC_PI_2,
+        );
+        // a camera turned a quarter counter clockwise sees the +y axis on its right
+        let on_screen = camera.view_projection_matrix() * glam::vec4(0.0, 1.0, 0.0, 1.0);
+
+        assert!(on_screen
+            .truncate()
+            .abs_diff_eq(glam::vec3(1.0, 0.0, 0.5), 1e-6));
+    }
+
+    fn viewport() -> Viewport {
+        // a 400x200 point tab, 100 points from the left and 50 from the top,
+        // on a display with 2 pixels per point
+        Viewport {
+            rect: egui::Rect::from_min_size(egui::pos2(100.0, 50.0), egui::vec2(400.0, 200.0)),
+            pixels_per_point: 2.0,
+        }
+    }
+
+    #[test]
+    fn screen_to_world_uses_viewport_offset_and_scale() {
+        let camera = OrthographicCamera::new(-2.0, 2.0, -1.0, 1.0, glam::Vec3::ZERO, 0.0);
+        let viewport = viewport();
+
+        let center = camera.screen_to_world((600.0, 300.0), &viewport);
+        assert!(center.truncate().abs_diff_eq(glam::Vec2::ZERO, 1e-6));
+
+        let top_left = camera.screen_to_world((200.0, 100.0), &viewport);
+        assert!(top_left.truncate().abs_diff_eq(glam::vec2(-2.0, 1.0), 1e-6));
+    }
+
+    #[test]
+    fn screen_to_world_follows_camera() {
+        let position = glam::vec3(3.0, -1.0, 0.0);
+        let camera =
+            OrthographicCamera::new(-2.0, 2.0, -1.0, 1.0, position, std::f32::consts::FRAC_PI_2);
+        let viewport = viewport();
+
+        // the right edge of the rotated camera looks up the world y axis
+        let right = camera.screen_to_world((1000.0, 300.0), &viewport);
+        assert!(right.truncate().abs_diff_eq(glam::vec2(3.0, 1.0), 1e-5));
+    }
+
+    #[test]
+    fn world_to_screen_is_inverse_of_screen_to_world() {
+        let camera = OrthographicCamera::new(-2.0, 2.0, -1.0, 1.0, glam::vec3(0.5, 0.25, 0.0), 0.3);
+        let viewport = viewport();
+        let world = glam::vec3(1.0, -0.5, 0.0);
+
+        let screen = camera.world_to_screen(world, &viewport);
+        let back = camera.screen_to_world(screen, &viewport);
+        assert!(back.truncate().abs_diff_eq(world.truncate(), 1e-5));
+    }
+
+    #[test]
+    fn perspective_camera_looks_down_forward() {
+        let mut camera = PerspectiveCamera::new(60.0_f32.to_radians(), 1.0, 0.1, 100.0);
+        camera.set_position(glam::vec3(0.0, 0.0, -5.0));
+        let center = camera
+            .view_projection_matrix()
+            .project_point3(glam::Vec3::ZERO);
+        assert!(center.truncate().abs_diff_eq(glam::Vec2::ZERO, 1e-6));
+
+        // turned a quarter to the right it looks down +x
+        camera.set_rotation(std::f32::consts::FRAC_PI_2, 0.0);
+        assert!(camera.forward().abs_diff_eq(glam::Vec3::X, 1e-6));
+        assert!(camera.right().abs_diff_eq(-glam::Vec3::Z, 1e-6));
+        assert!(camera.up().abs_diff_eq(glam::Vec3::Y, 1e-6));
+    }
+
+    #[test]
+    fn perspective_camera_clamps_pitch() {
+        let mut camera = PerspectiveCamera::new(60.0_f32.to_radians(), 1.0, 0.1, 100.0);
+        camera.set_rotation(0.0, 10.0);
+        assert!(camera.pitch() < std::f32::consts::FRAC_PI_2);
+        assert!(camera.view_matrix().is_finite());
+    }
+
+    #[test]
+    fn set_projection_keeps_view() {
+        let position = glam::vec3(1.0, 2.0, 0.0);
+        let mut camera = OrthographicCamera::new(-1.0, 1.0, -1.0, 1.0, position, 0.5);
+        camera.set_projection(-2.0, 2.0, -1.0, 1.0);
+        let expected = OrthographicCamera::new(-2.0, 2.0, -1.0, 1.0, position, 0.5);
+
+        assert_mat4_eq(
+            camera.view_projection_matrix(),
+            expected.view_projection_matrix(),
+        );
+    }
+
+    /// Draws into a 64x64 framebuffer and compares it with
+    /// `tests/snapshots/<name>.png`. These tests need OSMesa for a headless
+    /// OpenGL context, so they are ignored by default; run them with
+    /// `cargo test -- --ignored`.
+    fn assert_render_snapshot(name: &str, draw: impl FnOnce(&Rc<Context>, &mut Framebuffer)) {
+        let context = crate::sequoia::application::Application::new_headless(64, 64)
+            .expect("snapshot tests need a headless OpenGL context")
+            .context();
+        let mut framebuffer = Framebuffer::new(&context, 64, 64);
+        Renderer::set_clear_color(&mut framebuffer.surface(), glam::vec4(0.1, 0.1, 0.1, 1.0));
+        draw(&context, &mut framebuffer);
+        crate::sequoia::snapshot::assert_snapshot(
+            &framebuffer.read_pixels(),
+            format!(
+                "{}/tests/snapshots/{}.png",
+                env!("CARGO_MANIFEST_DIR"),
+                name
+            ),
+            2,
+        );
+    }
+
+    fn square(context: &Rc<Context>) -> VertexArray {
+        let layout = crate::sequoia::buffer::BufferLayout::new(vec![
+            crate::sequoia::buffer::BufferElement::new(
+                crate::sequoia::buffer::ShaderDataType::Float3,
+                "position",
+            ),
+        ]);
+        let vertices: [[f32; 3]; 4] = [
+            [-0.5, -0.5, 0.0],
+            [0.5, -0.5, 0.0],
+            [0.5, 0.5, 0.0],
+            [-0.5, 0.5, 0.0],
+        ];
+        VertexArray {
+            vertex_buffers: vec![VertexBuffer::new(context, layout, &vertices).unwrap()],
+            index_buffer: glium::IndexBuffer::new(
+                context,
+                glium::index::PrimitiveType::TrianglesList,
+                &[0, 1, 2, 0, 2, 3],
+            )
+            .unwrap(),
+        }
+    }
+
+    fn position_color(context: &Rc<Context>) -> Shader {
+        Shader::from_file(
+            context,
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/assets/shaders/position_color.glsl"
+            ),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs OSMesa"]
+    fn draw_indexed_snapshot() {
+        assert_render_snapshot("draw_indexed", |context, framebuffer| {
+            let camera = OrthographicCamera::new(-1.0, 1.0, -1.0, 1.0, glam::Vec3::ZERO, 0.0);
+            Renderer::draw_indexed(
+                &mut framebuffer.surface(),
+                &position_color(context),
+                &square(context),
+                camera.view_projection_matrix(),
+            );
+        });
+    }
+
+    #[test]
+    #[ignore = "needs OSMesa"]
+    fn camera_snapshot() {
+        assert_render_snapshot("camera", |context, framebuffer| {
+            let camera = OrthographicCamera::new(
+                -2.0,
+                2.0,
+                -2.0,
+                2.0,
+                glam::vec3(0.5, 0.25, 0.0),
+                30.0_f32.to_radians(),
+            );
+            Renderer::draw_indexed(
+                &mut framebuffer.surface(),
+                &position_color(context),
+                &square(context),
+                camera.view_projection_matrix(),
+            );
+        });
+    }
+
+    #[test]
+    #[ignore = "needs OSMesa"]
+    fn renderer2d_batch_snapshot() {
+        assert_render_snapshot("renderer2d_batch", |context, framebuffer| {
+            let camera = OrthographicCamera::new(-1.0, 1.0, -1.0, 1.0, glam::Vec3::ZERO, 0.0);
+            let checkerboard = crate::sequoia::texture::Texture2D::from_rgba(
+                context,
+                2,
+                2,
+                &[
+                    255, 255, 255, 255, 0, 0, 0, 255, //
+                    0, 0, 0, 255, 255, 255, 255, 255,
+                ],
+                crate::sequoia::texture::TextureSettings {
+                    filter: crate::sequoia::texture::TextureFilter::Nearest,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+            let mut renderer = crate::sequoia::renderer2d::Renderer2D::new(context).unwrap();
+            renderer.begin_scene(&camera);
+            renderer.draw_textured_quad(
+                glam::vec3(-0.5, 0.5, 0.0),
+                glam::vec2(0.8, 0.8),
+                &checkerboard,
+                2.0,
+                glam::Vec4::ONE,
+            );
+            for y in 0..4 {
+                for x in 0..4 {
+                    renderer.draw_quad(
+                        glam::vec3(x as f32 * 0.2 + 0.2, y as f32 * 0.2 - 0.9, 0.0),
+                        glam::vec2(0.15, 0.15),
+                        glam::vec4(x as f32 / 3.0, 0.5, y as f32 / 3.0, 1.0),
+                    );
+                }
+            }
+            renderer.draw_rotated_quad(
+                glam::vec3(-0.5, -0.5, 0.0),
+                glam::vec2(0.5, 0.5),
+                45.0_f32.to_radians(),
+                glam::vec4(0.8, 0.2, 0.3, 1.0),
+            );
+            renderer.end_scene(&mut framebuffer.surface());
+
+            let stats = renderer.stats();
+            assert_eq!(stats.draw_calls, 1);
+            assert_eq!(stats.quad_count, 18);
+        });
+    }
+}
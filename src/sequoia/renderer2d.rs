@@ -0,0 +1,417 @@
+use glium::{
+    backend::Facade,
+    uniforms::{UniformValue, Uniforms},
+    Surface,
+};
+
+use super::{
+    renderer::OrthographicCamera,
+    shader::{Shader, ShaderError},
+    texture::Texture2D,
+};
+
+const MAX_QUADS: usize = 10_000;
+const MAX_VERTICES: usize = MAX_QUADS * 4;
+// OpenGL guarantees at least 16 texture units in the fragment shader
+const MAX_TEXTURE_SLOTS: usize = 16;
+// slot 0 always holds a white texture, used by quads without a texture
+const WHITE_TEXTURE_SLOT: usize = 0;
+
+// corners of a unit quad centered on the origin, counter clockwise
+const QUAD_POSITIONS: [[f32; 4]; 4] = [
+    [-0.5, -0.5, 0.0, 1.0],
+    [0.5, -0.5, 0.0, 1.0],
+    [0.5, 0.5, 0.0, 1.0],
+    [-0.5, 0.5, 0.0, 1.0],
+];
+
+const QUAD_TEX_COORDS: [[f32; 2]; 4] = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct QuadVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+    tex_coord: [f32; 2],
+    tex_index: f32,
+    tiling_factor: f32,
+}
+
+glium::implement_vertex!(
+    QuadVertex,
+    position,
+    color,
+    tex_coord,
+    tex_index,
+    tiling_factor
+);
+
+/// A run of vertices that can be drawn with a single draw call.
+struct Batch<T> {
+    start: usize,
+    texture_slots: Vec<T>,
+}
+
+/// Splits quads into batches: a new batch starts once the current one has
+/// `MAX_VERTICES` vertices or would need more than `MAX_TEXTURE_SLOTS`
+/// textures. Generic over the texture, so it works without a GL context.
+struct Batcher<T> {
+    white_texture: T,
+    vertices: Vec<QuadVertex>,
+    batches: Vec<Batch<T>>,
+}
+
+impl<T: Clone + PartialEq> Batcher<T> {
+    fn new(white_texture: T) -> Self {
+        Self {
+            white_texture,
+            vertices: Vec::with_capacity(MAX_VERTICES),
+            batches: Vec::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.vertices.clear();
+        self.batches.clear();
+    }
+
+    /// Starts a new batch, with only the white texture bound.
+    fn next_batch(&mut self) {
+        self.batches.push(Batch {
+            start: self.vertices.len(),
+            texture_slots: vec![self.white_texture.clone()],
+        });
+    }
+
+    fn batch_is_full(&self) -> bool {
+        match self.batches.last() {
+            Some(batch) => self.vertices.len() - batch.start >= MAX_VERTICES,
+            None => true,
+        }
+    }
+
+    /// Returns the slot `texture` is bound to in the current batch, binding it
+    /// first if needed.
+    fn texture_slot(&mut self, texture: &T) -> usize {
+        if self.batch_is_full() {
+            self.next_batch();
+        }
+        let batch = self.batches.last_mut().unwrap();
+        if let Some(slot) = batch.texture_slots.iter().position(|t| t == texture) {
+            return slot;
+        }
+        if batch.texture_slots.len() == MAX_TEXTURE_SLOTS {
+            self.next_batch();
+        }
+        let batch = self.batches.last_mut().unwrap();
+        batch.texture_slots.push(texture.clone());
+        batch.texture_slots.len() - 1
+    }
+
+    /// Adds the corners of a quad to the current batch. Textured quads have to
+    /// get their slot from `texture_slot` first.
+    fn push_quad(&mut self, corners: [QuadVertex; 4]) {
+        // texture_slot already made room for textured quads
+        if self.batch_is_full() {
+            self.next_batch();
+        }
+        self.vertices.extend(corners);
+    }
+
+    /// The vertices and textures of every batch, in drawing order.
+    fn batches(&self) -> impl Iterator<Item = (&[QuadVertex], &[T])> {
+        self.batches.iter().enumerate().map(|(i, batch)| {
+            let end = self
+                .batches
+                .get(i + 1)
+                .map_or(self.vertices.len(), |next| next.start);
+            (
+                &self.vertices[batch.start..end],
+                batch.texture_slots.as_slice(),
+            )
+        })
+    }
+}
+
+struct BatchUniforms<'a> {
+    view_projection_matrix: [[f32; 4]; 4],
+    texture_slots: &'a [Texture2D],
+}
+
+impl Uniforms for BatchUniforms<'_> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut visit: F) {
+        visit(
+            "u_ViewProjection",
+            UniformValue::Mat4(self.view_projection_matrix),
+        );
+        for (slot, texture) in self.texture_slots.iter().enumerate() {
+            visit(
+                &format!("u_Textures[{}]", slot),
+                UniformValue::SrgbTexture2d(
+                    texture.texture(),
+                    Some(texture.settings().sampler_behavior()),
+                ),
+            );
+        }
+    }
+}
+
+/// Counters for the scene drawn between `begin_scene` and `end_scene`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Statistics {
+    pub draw_calls: u32,
+    pub quad_count: u32,
+}
+
+impl Statistics {
+    pub fn vertex_count(&self) -> u32 {
+        self.quad_count * 4
+    }
+
+    pub fn index_count(&self) -> u32 {
+        self.quad_count * 6
+    }
+}
+
+/// Collects quads into a single dynamic vertex buffer and draws them in as few
+/// draw calls as possible. A new draw call is only needed once the vertex
+/// buffer or the texture slots of the current batch are full.
+///
+/// ```ignore
+/// renderer.begin_scene(&camera);
+/// renderer.draw_quad(position, size, color);
+/// renderer.end_scene(&mut target);
+/// ```
+///
+/// The shader is built into the binary, so the renderer works from any working
+/// directory. That also means it is not hot reloaded; restart the application
+/// after editing `assets/shaders/renderer2d.glsl`.
+pub struct Renderer2D {
+    vertex_buffer: glium::VertexBuffer<QuadVertex>,
+    index_buffer: glium::IndexBuffer<u32>,
+    shader: Shader,
+    batcher: Batcher<Texture2D>,
+    view_projection_matrix: glam::Mat4,
+    stats: Statistics,
+}
+
+impl Renderer2D {
+    pub fn new(display: &impl Facade) -> Result<Self, ShaderError> {
+        let vertex_buffer = glium::VertexBuffer::empty_dynamic(display, MAX_VERTICES).unwrap();
+
+        // the index pattern is the same for every quad, so it only has to be uploaded once
+        let indices: Vec<u32> = (0..MAX_QUADS as u32)
+            .flat_map(|quad| {
+                let offset = quad * 4;
+                [
+                    offset,
+                    offset + 1,
+                    offset + 2,
+                    offset + 2,
+                    offset + 3,
+                    offset,
+                ]
+            })
+            .collect();
+        let index_buffer = glium::IndexBuffer::new(
+            display,
+            glium::index::PrimitiveType::TrianglesList,
+            &indices,
+        )
+        .unwrap();
+
+        let shader = Shader::from_source(
+            display,
+            "renderer2d",
+            include_str!("../../assets/shaders/renderer2d.glsl"),
+        )?;
+
+        let white_texture =
+            Texture2D::from_rgba(display, 1, 1, &[255, 255, 255, 255], Default::default()).unwrap();
+
+        Ok(Self {
+            vertex_buffer,
+            index_buffer,
+            shader,
+            batcher: Batcher::new(white_texture),
+            view_projection_matrix: glam::Mat4::IDENTITY,
+            stats: Statistics::default(),
+        })
+    }
+
+    /// Starts a new scene and resets the statistics.
+    pub fn begin_scene(&mut self, camera: &OrthographicCamera) {
+        self.view_projection_matrix = camera.view_projection_matrix();
+        self.batcher.clear();
+        self.stats = Statistics::default();
+    }
+
+    /// Draws everything submitted since `begin_scene`.
+    pub fn end_scene<S: Surface>(&mut self, target: &mut S) {
+        let draw_parameters = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            ..Default::default()
+        };
+
+        for (vertices, texture_slots) in self.batcher.batches() {
+            let vertex_buffer = self.vertex_buffer.slice(0..vertices.len()).unwrap();
+            vertex_buffer.write(vertices);
+            let index_buffer = self.index_buffer.slice(0..vertices.len() / 4 * 6).unwrap();
+            let uniforms = BatchUniforms {
+                view_projection_matrix: self.view_projection_matrix.to_cols_array_2d(),
+                texture_slots,
+            };
+            match target.draw(
+                vertex_buffer,
+                index_buffer,
+                self.shader.program(),
+                &uniforms,
+                &draw_parameters,
+            ) {
+                Ok(_) => self.stats.draw_calls += 1,
+                Err(why) => error!("Renderer2D draw error: {}", why),
+            }
+        }
+        self.batcher.clear();
+    }
+
+    pub fn draw_quad(&mut self, position: glam::Vec3, size: glam::Vec2, color: glam::Vec4) {
+        self.draw_rotated_quad(position, size, 0.0, color);
+    }
+
+    /// Like `draw_quad`, rotated counter clockwise around its center by
+    /// `rotation` radians.
+    pub fn draw_rotated_quad(
+        &mut self,
+        position: glam::Vec3,
+        size: glam::Vec2,
+        rotation: f32,
+        color: glam::Vec4,
+    ) {
+        let transform = Self::transform(position, size, rotation);
+        self.submit_quad(transform, WHITE_TEXTURE_SLOT, 1.0, color);
+    }
+
+    /// Draws `texture` stretched over the quad, repeated `tiling_factor` times
+    /// in each direction and multiplied by `tint`.
+    pub fn draw_textured_quad(
+        &mut self,
+        position: glam::Vec3,
+        size: glam::Vec2,
+        texture: &Texture2D,
+        tiling_factor: f32,
+        tint: glam::Vec4,
+    ) {
+        self.draw_rotated_textured_quad(position, size, 0.0, texture, tiling_factor, tint);
+    }
+
+    pub fn draw_rotated_textured_quad(
+        &mut self,
+        position: glam::Vec3,
+        size: glam::Vec2,
+        rotation: f32,
+        texture: &Texture2D,
+        tiling_factor: f32,
+        tint: glam::Vec4,
+    ) {
+        let transform = Self::transform(position, size, rotation);
+        let slot = self.batcher.texture_slot(texture);
+        self.submit_quad(transform, slot, tiling_factor, tint);
+    }
+
+    fn transform(position: glam::Vec3, size: glam::Vec2, rotation: f32) -> glam::Mat4 {
+        glam::Mat4::from_translation(position)
+            * glam::Mat4::from_rotation_z(rotation)
+            * glam::Mat4::from_scale(size.extend(1.0))
+    }
+
+    fn submit_quad(
+        &mut self,
+        transform: glam::Mat4,
+        tex_index: usize,
+        tiling_factor: f32,
+        color: glam::Vec4,
+    ) {
+        let corners = std::array::from_fn(|i| QuadVertex {
+            position: (transform * glam::Vec4::from(QUAD_POSITIONS[i]))
+                .truncate()
+                .to_array(),
+            color: color.to_array(),
+            tex_coord: QUAD_TEX_COORDS[i],
+            tex_index: tex_index as f32,
+            tiling_factor,
+        });
+        self.batcher.push_quad(corners);
+        self.stats.quad_count += 1;
+    }
+
+    pub fn stats(&self) -> Statistics {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: u32 = 0;
+
+    fn quad(tex_index: usize) -> [QuadVertex; 4] {
+        [QuadVertex {
+            position: [0.0; 3],
+            color: [1.0; 4],
+            tex_coord: [0.0; 2],
+            tex_index: tex_index as f32,
+            tiling_factor: 1.0,
+        }; 4]
+    }
+
+    fn batch_sizes(batcher: &Batcher<u32>) -> Vec<(usize, usize)> {
+        batcher
+            .batches()
+            .map(|(vertices, textures)| (vertices.len(), textures.len()))
+            .collect()
+    }
+
+    #[test]
+    fn batches_flush_when_the_vertex_buffer_is_full() {
+        let mut batcher = Batcher::new(WHITE);
+        for _ in 0..MAX_QUADS {
+            batcher.push_quad(quad(WHITE_TEXTURE_SLOT));
+        }
+        assert_eq!(batch_sizes(&batcher), [(MAX_VERTICES, 1)]);
+
+        // a textured quad needs a new batch too, and binds its texture there
+        assert_eq!(batcher.texture_slot(&7), 1);
+        batcher.push_quad(quad(1));
+        batcher.push_quad(quad(WHITE_TEXTURE_SLOT));
+        assert_eq!(batch_sizes(&batcher), [(MAX_VERTICES, 1), (8, 2)]);
+
+        batcher.clear();
+        assert_eq!(batcher.batches().count(), 0);
+    }
+
+    #[test]
+    fn batches_flush_when_the_texture_slots_are_full() {
+        let mut batcher = Batcher::new(WHITE);
+        for texture in 1..MAX_TEXTURE_SLOTS as u32 {
+            let slot = batcher.texture_slot(&texture);
+            assert_eq!(slot, texture as usize);
+            batcher.push_quad(quad(slot));
+        }
+        // textures that are already bound reuse their slot
+        assert_eq!(batcher.texture_slot(&3), 3);
+        batcher.push_quad(quad(3));
+        assert_eq!(batch_sizes(&batcher), [(64, MAX_TEXTURE_SLOTS)]);
+
+        // one texture too many, the new batch starts with only white bound
+        assert_eq!(batcher.texture_slot(&100), 1);
+        batcher.push_quad(quad(1));
+        assert_eq!(batcher.texture_slot(&1), 2);
+        batcher.push_quad(quad(2));
+
+        let batches: Vec<_> = batcher.batches().collect();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[1].0, [quad(1), quad(2)].concat());
+        assert_eq!(batches[1].1, [WHITE, 100, 1]);
+    }
+}
@@ -0,0 +1,631 @@
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use glium::{
+    backend::Facade,
+    uniforms::{UniformValue, Uniforms},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    Geometry,
+    Fragment,
+}
+
+impl ShaderStage {
+    fn from_type(name: &str) -> Option<ShaderStage> {
+        match name {
+            "vertex" => Some(ShaderStage::Vertex),
+            "geometry" => Some(ShaderStage::Geometry),
+            "fragment" | "pixel" => Some(ShaderStage::Fragment),
+            _ => None,
+        }
+    }
+
+    fn from_glium(shader_type: glium::program::ShaderType) -> Option<ShaderStage> {
+        match shader_type {
+            glium::program::ShaderType::Vertex => Some(ShaderStage::Vertex),
+            glium::program::ShaderType::Geometry => Some(ShaderStage::Geometry),
+            glium::program::ShaderType::Fragment => Some(ShaderStage::Fragment),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::Geometry => write!(f, "geometry"),
+            ShaderStage::Fragment => write!(f, "fragment"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    /// The source is missing a stage or has an unknown `#type`.
+    Parse {
+        name: String,
+        message: String,
+    },
+    /// The compiler log, with every message that refers to a line followed by
+    /// that line of the source file.
+    Compile {
+        name: String,
+        stage: ShaderStage,
+        message: String,
+    },
+    Link {
+        name: String,
+        message: String,
+    },
+    Other {
+        name: String,
+        error: glium::program::ProgramCreationError,
+    },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::Io { path, error } => {
+                write!(f, "could not read shader {}: {}", path.display(), error)
+            }
+            ShaderError::Parse { name, message } => {
+                write!(f, "could not parse shader {}: {}", name, message)
+            }
+            ShaderError::Compile {
+                name,
+                stage,
+                message,
+            } => write!(
+                f,
+                "could not compile {} stage of shader {}:\n{}",
+                stage, name, message
+            ),
+            ShaderError::Link { name, message } => {
+                write!(f, "could not link shader {}:\n{}", name, message)
+            }
+            ShaderError::Other { name, error } => {
+                write!(f, "could not create shader {}: {}", name, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// The source of one stage, and the line of the file it starts on.
+struct StageSource {
+    source: String,
+    first_line: usize,
+}
+
+/// Splits a file into stages at every `#type <stage>` line.
+fn split_stages(
+    name: &str,
+    source: &str,
+) -> Result<HashMap<ShaderStage, StageSource>, ShaderError> {
+    let mut stages = HashMap::new();
+    let mut current: Option<(ShaderStage, StageSource)> = None;
+
+    for (i, line) in source.lines().enumerate() {
+        if let Some(stage_type) = line.trim().strip_prefix("#type") {
+            let stage_type = stage_type.trim();
+            let stage = ShaderStage::from_type(stage_type).ok_or_else(|| ShaderError::Parse {
+                name: name.to_owned(),
+                message: format!("line {}: unknown shader type '{}'", i + 1, stage_type),
+            })?;
+            if let Some((stage, source)) = current.take() {
+                stages.insert(stage, source);
+            }
+            if stages.contains_key(&stage) {
+                return Err(ShaderError::Parse {
+                    name: name.to_owned(),
+                    message: format!("line {}: duplicate {} stage", i + 1, stage),
+                });
+            }
+            current = Some((
+                stage,
+                StageSource {
+                    source: String::new(),
+                    // the stage starts on the line after the marker, lines are 1 based
+                    first_line: i + 2,
+                },
+            ));
+        } else if let Some((_, stage_source)) = current.as_mut() {
+            stage_source.source.push_str(line);
+            stage_source.source.push('\n');
+        } else if !line.trim().is_empty() {
+            return Err(ShaderError::Parse {
+                name: name.to_owned(),
+                message: format!("line {}: source before the first #type", i + 1),
+            });
+        }
+    }
+    if let Some((stage, source)) = current.take() {
+        stages.insert(stage, source);
+    }
+
+    for required in [ShaderStage::Vertex, ShaderStage::Fragment] {
+        if !stages.contains_key(&required) {
+            return Err(ShaderError::Parse {
+                name: name.to_owned(),
+                message: format!("missing {} stage", required),
+            });
+        }
+    }
+    Ok(stages)
+}
+
+/// Finds the line number in a compiler message about the first source
+/// string, which is the only one a stage is compiled from. Drivers disagree on
+/// the format, so this handles `0:12(5):` (Mesa), `0(12) :` (Nvidia) and
+/// `ERROR: 0:12:` (AMD and others), always at the start of the message.
+fn compiler_message_line(message: &str) -> Option<usize> {
+    let message = message.trim_start();
+    let message = ["ERROR: ", "WARNING: "]
+        .iter()
+        .find_map(|severity| message.strip_prefix(severity))
+        .unwrap_or(message);
+
+    let (rest, terminators): (&str, &[char]) = if let Some(rest) = message.strip_prefix("0:") {
+        (rest, &['(', ':'])
+    } else if let Some(rest) = message.strip_prefix("0(") {
+        (rest, &[')'])
+    } else {
+        return None;
+    };
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let terminator = rest[digits..].chars().next()?;
+    if digits == 0 || !terminators.contains(&terminator) {
+        return None;
+    }
+    rest[..digits].parse().ok()
+}
+
+/// Rewrites the line numbers in a compiler log to lines in the shader file,
+/// and quotes the offending line under each message.
+fn annotate_log(log: &str, file_source: &str, first_line: usize) -> String {
+    let file_lines: Vec<&str> = file_source.lines().collect();
+    let mut annotated = String::new();
+    for message in log.lines().filter(|line| !line.trim().is_empty()) {
+        match compiler_message_line(message) {
+            Some(stage_line) => {
+                let file_line = stage_line + first_line - 1;
+                annotated.push_str(&format!("line {}: {}\n", file_line, message.trim()));
+                if let Some(text) = file_lines.get(file_line - 1) {
+                    annotated.push_str(&format!("    {} | {}\n", file_line, text));
+                }
+            }
+            None => {
+                annotated.push_str(message.trim());
+                annotated.push('\n');
+            }
+        }
+    }
+    annotated
+}
+
+#[derive(Debug, Clone, Copy)]
+enum UniformData {
+    Int(i32),
+    Float(f32),
+    Float2([f32; 2]),
+    Float3([f32; 3]),
+    Float4([f32; 4]),
+    Mat3([[f32; 3]; 3]),
+    Mat4([[f32; 4]; 4]),
+}
+
+impl UniformData {
+    fn value(&self) -> UniformValue<'static> {
+        match *self {
+            UniformData::Int(v) => UniformValue::SignedInt(v),
+            UniformData::Float(v) => UniformValue::Float(v),
+            UniformData::Float2(v) => UniformValue::Vec2(v),
+            UniformData::Float3(v) => UniformValue::Vec3(v),
+            UniformData::Float4(v) => UniformValue::Vec4(v),
+            UniformData::Mat3(v) => UniformValue::Mat3(v),
+            UniformData::Mat4(v) => UniformValue::Mat4(v),
+        }
+    }
+}
+
+/// A file on disk, polled for changes through its modification time.
+#[derive(Debug)]
+struct FileWatch {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl FileWatch {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_owned(),
+            modified: Self::modified_time(path),
+        }
+    }
+
+    fn modified_time(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Calls `load` if the file changed since it was last loaded, and returns
+    /// what it loaded. A failed load is not retried until the file changes
+    /// again. A file that disappears counts as a change, so `load` can report
+    /// it.
+    fn reload<T, E>(&mut self, load: impl FnOnce(&Path) -> Result<T, E>) -> Result<Option<T>, E> {
+        let modified = Self::modified_time(&self.path);
+        if modified == self.modified {
+            return Ok(None);
+        }
+        self.modified = modified;
+        load(&self.path).map(Some)
+    }
+}
+
+/// A compiled program and the uniform values set on it.
+///
+/// Sources are a single GLSL file with a `#type vertex` and a `#type fragment`
+/// line in front of each stage (and optionally `#type geometry`).
+pub struct Shader {
+    name: String,
+    program: glium::Program,
+    uniforms: HashMap<String, UniformData>,
+    // set for shaders loaded from disk, so they can be reloaded
+    watch: Option<FileWatch>,
+}
+
+impl Shader {
+    /// Loads a shader, named after the file stem.
+    pub fn from_file(display: &impl Facade, path: impl AsRef<Path>) -> Result<Self, ShaderError> {
+        let path = path.as_ref();
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        // watch before reading, so a write during the read is picked up later
+        let watch = FileWatch::new(path);
+        let source = fs::read_to_string(path).map_err(|error| ShaderError::Io {
+            path: path.to_owned(),
+            error,
+        })?;
+        let mut shader = Self::from_source(display, &name, &source)?;
+        shader.watch = Some(watch);
+        Ok(shader)
+    }
+
+    pub fn from_source(
+        display: &impl Facade,
+        name: &str,
+        source: &str,
+    ) -> Result<Self, ShaderError> {
+        let stages = split_stages(name, source)?;
+        let stage_source = |stage| stages.get(&stage).map(|s: &StageSource| s.source.as_str());
+
+        let program = glium::Program::new(
+            display,
+            glium::program::ProgramCreationInput::SourceCode {
+                vertex_shader: stage_source(ShaderStage::Vertex).unwrap(),
+                tessellation_control_shader: None,
+                tessellation_evaluation_shader: None,
+                geometry_shader: stage_source(ShaderStage::Geometry),
+                fragment_shader: stage_source(ShaderStage::Fragment).unwrap(),
+                transform_feedback_varyings: None,
+                outputs_srgb: false,
+                uses_point_size: false,
+            },
+        )
+        .map_err(|error| match error {
+            glium::program::ProgramCreationError::CompilationError(log, shader_type) => {
+                match ShaderStage::from_glium(shader_type) {
+                    Some(stage) => ShaderError::Compile {
+                        name: name.to_owned(),
+                        stage,
+                        message: annotate_log(&log, source, stages[&stage].first_line),
+                    },
+                    None => ShaderError::Other {
+                        name: name.to_owned(),
+                        error: glium::program::ProgramCreationError::CompilationError(
+                            log,
+                            shader_type,
+                        ),
+                    },
+                }
+            }
+            glium::program::ProgramCreationError::LinkingError(log) => ShaderError::Link {
+                name: name.to_owned(),
+                message: log,
+            },
+            error => ShaderError::Other {
+                name: name.to_owned(),
+                error,
+            },
+        })?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            program,
+            uniforms: HashMap::new(),
+            watch: None,
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.watch.as_ref().map(|watch| watch.path.as_path())
+    }
+
+    /// Recompiles the shader if its file changed since it was last loaded.
+    /// Returns whether the program was replaced. On failure the old program
+    /// and uniforms are kept, and the same file is not retried until it
+    /// changes again.
+    pub fn reload_if_changed(&mut self, display: &impl Facade) -> Result<bool, ShaderError> {
+        let Some(watch) = self.watch.as_mut() else {
+            return Ok(false);
+        };
+        match watch.reload(|path| Self::from_file(display, path))? {
+            Some(reloaded) => {
+                self.program = reloaded.program;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn program(&self) -> &glium::Program {
+        &self.program
+    }
+
+    pub fn set_int(&mut self, name: &str, value: i32) {
+        self.uniforms
+            .insert(name.to_owned(), UniformData::Int(value));
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.uniforms
+            .insert(name.to_owned(), UniformData::Float(value));
+    }
+
+    pub fn set_float2(&mut self, name: &str, value: glam::Vec2) {
+        self.uniforms
+            .insert(name.to_owned(), UniformData::Float2(value.to_array()));
+    }
+
+    pub fn set_float3(&mut self, name: &str, value: glam::Vec3) {
+        self.uniforms
+            .insert(name.to_owned(), UniformData::Float3(value.to_array()));
+    }
+
+    pub fn set_float4(&mut self, name: &str, value: glam::Vec4) {
+        self.uniforms
+            .insert(name.to_owned(), UniformData::Float4(value.to_array()));
+    }
+
+    pub fn set_mat3(&mut self, name: &str, value: glam::Mat3) {
+        self.uniforms
+            .insert(name.to_owned(), UniformData::Mat3(value.to_cols_array_2d()));
+    }
+
+    pub fn set_mat4(&mut self, name: &str, value: glam::Mat4) {
+        self.uniforms
+            .insert(name.to_owned(), UniformData::Mat4(value.to_cols_array_2d()));
+    }
+}
+
+impl Uniforms for Shader {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut visit: F) {
+        for (name, data) in self.uniforms.iter() {
+            visit(name, data.value());
+        }
+    }
+}
+
+/// Shaders keyed by name.
+#[derive(Default)]
+pub struct ShaderLibrary {
+    shaders: HashMap<String, Shader>,
+}
+
+impl ShaderLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a shader under its own name, replacing any shader with that name.
+    pub fn add(&mut self, shader: Shader) {
+        self.shaders.insert(shader.name().to_owned(), shader);
+    }
+
+    /// Loads a shader from disk, named after the file stem.
+    pub fn load(
+        &mut self,
+        display: &impl Facade,
+        path: impl AsRef<Path>,
+    ) -> Result<&mut Shader, ShaderError> {
+        let shader = Shader::from_file(display, path)?;
+        let name = shader.name().to_owned();
+        self.add(shader);
+        Ok(self.shaders.get_mut(&name).unwrap())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Shader> {
+        self.shaders.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Shader> {
+        self.shaders.get_mut(name)
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.shaders.contains_key(name)
+    }
+
+    /// Recompiles every shader whose file changed on disk. Meant to be called
+    /// once per frame, before anything is drawn. Errors are logged and the
+    /// previous program stays in use, so a typo never takes the app down.
+    pub fn reload_changed(&mut self, display: &impl Facade) {
+        for shader in self.shaders.values_mut() {
+            match shader.reload_if_changed(display) {
+                Ok(true) => info!("Reloaded shader {}", shader.name()),
+                Ok(false) => {}
+                Err(why) => error!("{}", why),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // blank lines before the first stage are skipped
+    const SOURCE: &str = "
+#type vertex
+void main() {
+    gl_Position = vec4(0.0);
+}
+#type fragment
+out vec4 color;
+void main() { color = vec4(1.0); }
+";
+
+    fn parse_message(result: Result<HashMap<ShaderStage, StageSource>, ShaderError>) -> String {
+        match result {
+            Err(ShaderError::Parse { message, .. }) => message,
+            Err(error) => panic!("expected a parse error, got {}", error),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+
+    fn touch(path: &Path, seconds_later: u64) {
+        let modified = fs::metadata(path).unwrap().modified().unwrap();
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified + std::time::Duration::from_secs(seconds_later))
+            .unwrap();
+    }
+
+    #[test]
+    fn file_watch_reloads_once_per_change() {
+        let path = std::env::temp_dir().join(format!("sequoia-watch-{}.glsl", std::process::id()));
+        fs::write(&path, "first").unwrap();
+        let mut watch = FileWatch::new(&path);
+        let read = |path: &Path| fs::read_to_string(path);
+        assert_eq!(watch.reload(read).unwrap(), None);
+
+        fs::write(&path, "second").unwrap();
+        touch(&path, 1);
+        assert_eq!(watch.reload(read).unwrap().as_deref(), Some("second"));
+        assert_eq!(watch.reload(read).unwrap(), None);
+
+        // a failed load, like a shader that doesn't compile, isn't retried
+        // until the file changes again
+        touch(&path, 1);
+        assert!(watch.reload(|_| Err::<String, _>("compile error")).is_err());
+        assert_eq!(watch.reload(read).unwrap(), None);
+        touch(&path, 1);
+        assert_eq!(watch.reload(read).unwrap().as_deref(), Some("second"));
+
+        // a deleted file is reported once
+        fs::remove_file(&path).unwrap();
+        assert!(watch.reload(read).is_err());
+        assert_eq!(watch.reload(read).unwrap(), None);
+    }
+
+    #[test]
+    fn split_stages_at_type_lines() {
+        let stages = split_stages("test", SOURCE).unwrap();
+        assert_eq!(stages.len(), 2);
+
+        let vertex = &stages[&ShaderStage::Vertex];
+        assert!(vertex.source.starts_with("void main() {\n"));
+        assert!(vertex.source.ends_with("}\n"));
+        assert_eq!(vertex.first_line, 3);
+
+        let fragment = &stages[&ShaderStage::Fragment];
+        assert_eq!(
+            fragment.source,
+            "out vec4 color;\nvoid main() { color = vec4(1.0); }\n"
+        );
+        assert_eq!(fragment.first_line, 7);
+    }
+
+    #[test]
+    fn split_stages_rejects_bad_files() {
+        let message = parse_message(split_stages("test", "#type pixel\n#type compute\n"));
+        assert_eq!(message, "line 2: unknown shader type 'compute'");
+
+        let message = parse_message(split_stages("test", "#type vertex\n#type vertex\n"));
+        assert_eq!(message, "line 2: duplicate vertex stage");
+
+        let message = parse_message(split_stages("test", "int x;\n#type vertex\n"));
+        assert_eq!(message, "line 1: source before the first #type");
+
+        let message = parse_message(split_stages("test", "#type vertex\n"));
+        assert_eq!(message, "missing fragment stage");
+    }
+
+    #[test]
+    fn compiler_message_line_formats() {
+        assert_eq!(
+            compiler_message_line("0:12(5): error: `x' undeclared"),
+            Some(12)
+        );
+        assert_eq!(
+            compiler_message_line("0(12) : error C1008: undefined variable \"x\""),
+            Some(12)
+        );
+        assert_eq!(
+            compiler_message_line("ERROR: 0:12: 'x' : undeclared identifier"),
+            Some(12)
+        );
+        assert_eq!(compiler_message_line("  0:3(1): warning"), Some(3));
+
+        // other source strings, and numbers later in the message, don't count
+        assert_eq!(compiler_message_line("1:10(3): error"), None);
+        assert_eq!(compiler_message_line("10(3) : error"), None);
+        assert_eq!(compiler_message_line("error: array size 0:2(1)"), None);
+        assert_eq!(compiler_message_line("0:(5): error"), None);
+        assert_eq!(compiler_message_line("0(12: error"), None);
+        assert_eq!(compiler_message_line("error: linking failed"), None);
+    }
+
+    #[test]
+    fn annotate_log_maps_stage_lines_to_file_lines() {
+        let stages = split_stages("test", SOURCE).unwrap();
+        let fragment = &stages[&ShaderStage::Fragment];
+        let log = "0:2(15): error: `vec4' undeclared\n\nnote: one error\n";
+        assert_eq!(
+            annotate_log(log, SOURCE, fragment.first_line),
+            "line 8: 0:2(15): error: `vec4' undeclared\n\
+             \x20   8 | void main() { color = vec4(1.0); }\n\
+             note: one error\n"
+        );
+
+        // a line past the end of the file is reported without a quote
+        let vertex = &stages[&ShaderStage::Vertex];
+        assert_eq!(
+            annotate_log("0(40) : error", SOURCE, vertex.first_line),
+            "line 42: 0(40) : error\n"
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+
+/// Set this environment variable to overwrite snapshots with the current
+/// output instead of comparing against them.
+pub const UPDATE_SNAPSHOTS_VAR: &str = "SEQUOIA_UPDATE_SNAPSHOTS";
+
+/// How two images of the same size differ.
+#[derive(Debug, Clone)]
+pub struct Diff {
+    /// Pixels with any channel more than the tolerance apart.
+    pub mismatched_pixels: usize,
+    /// The largest difference of any channel of any pixel.
+    pub max_difference: u8,
+    /// The expected image faded to gray, with mismatched pixels in red.
+    pub image: image::RgbaImage,
+}
+
+impl Diff {
+    pub fn is_match(&self) -> bool {
+        self.mismatched_pixels == 0
+    }
+}
+
+/// Compares two images of the same size, allowing each channel of each pixel
+/// to be off by up to `tolerance`.
+///
+/// # Panics
+///
+/// If the images differ in size.
+pub fn diff(actual: &image::RgbaImage, expected: &image::RgbaImage, tolerance: u8) -> Diff {
+    assert_eq!(
+        actual.dimensions(),
+        expected.dimensions(),
+        "images differ in size"
+    );
+
+    let mut mismatched_pixels = 0;
+    let mut max_difference = 0;
+    let image = image::RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let a = actual.get_pixel(x, y).0;
+        let e = expected.get_pixel(x, y).0;
+        let difference = a
+            .iter()
+            .zip(e)
+            .map(|(a, e)| a.abs_diff(e))
+            .max()
+            .unwrap_or(0);
+        max_difference = max_difference.max(difference);
+        if difference > tolerance {
+            mismatched_pixels += 1;
+            image::Rgba([255, 0, 0, 255])
+        } else {
+            let gray = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 3 / 4 + 32) as u8;
+            image::Rgba([gray, gray, gray, 255])
+        }
+    });
+
+    Diff {
+        mismatched_pixels,
+        max_difference,
+        image,
+    }
+}
+
+/// Compares `actual` against the PNG at `path`, allowing each channel of each
+/// pixel to be off by up to `tolerance`.
+///
+/// With `SEQUOIA_UPDATE_SNAPSHOTS` set, the image is saved there instead, to
+/// be reviewed and checked in. A missing PNG fails like a mismatch. On a mismatch
+/// the image is saved next to it as `<name>.actual.png`, along with a
+/// `<name>.diff.png` that shows the mismatched pixels in red.
+///
+/// # Panics
+///
+/// If the images don't match or there is no snapshot, which fails the test.
+pub fn assert_snapshot(actual: &image::RgbaImage, path: impl AsRef<Path>, tolerance: u8) {
+    let path = path.as_ref();
+
+    if std::env::var_os(UPDATE_SNAPSHOTS_VAR).is_some() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        actual.save(path).unwrap();
+        warn!("Saved snapshot {}", path.display());
+        return;
+    }
+    if !path.exists() {
+        let actual_path = sibling(path, "actual");
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        actual.save(&actual_path).unwrap();
+        panic!(
+            "there is no snapshot {}, the image is saved to {}; run with {}=1 to create it",
+            path.display(),
+            actual_path.display(),
+            UPDATE_SNAPSHOTS_VAR
+        );
+    }
+
+    let expected = match image::open(path) {
+        Ok(expected) => expected.to_rgba8(),
+        Err(why) => panic!("could not load snapshot {}: {}", path.display(), why),
+    };
+    let actual_path = sibling(path, "actual");
+    if actual.dimensions() != expected.dimensions() {
+        actual.save(&actual_path).unwrap();
+        panic!(
+            "snapshot {} is {:?}, but the image is {:?}, saved to {}",
+            path.display(),
+            expected.dimensions(),
+            actual.dimensions(),
+            actual_path.display()
+        );
+    }
+
+    let diff = diff(actual, &expected, tolerance);
+    if !diff.is_match() {
+        let diff_path = sibling(path, "diff");
+        actual.save(&actual_path).unwrap();
+        diff.image.save(&diff_path).unwrap();
+        panic!(
+            "{} pixels differ from snapshot {} by more than {} (up to {}), see {} and {}",
+            diff.mismatched_pixels,
+            path.display(),
+            tolerance,
+            diff.max_difference,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+/// `dir/name.png` becomes `dir/name.<suffix>.png`.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_allows_tolerance() {
+        let expected = image::RgbaImage::from_pixel(4, 2, image::Rgba([100, 100, 100, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(0, 0, image::Rgba([102, 100, 100, 255]));
+        actual.put_pixel(3, 1, image::Rgba([100, 90, 100, 255]));
+
+        let diff = diff(&actual, &expected, 2);
+        assert_eq!(diff.mismatched_pixels, 1);
+        assert_eq!(diff.max_difference, 10);
+        assert_eq!(diff.image.get_pixel(3, 1).0, [255, 0, 0, 255]);
+        assert_ne!(diff.image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert!(super::diff(&actual, &expected, 10).is_match());
+    }
+}
@@ -0,0 +1,216 @@
+use std::{fmt, path::Path, rc::Rc};
+
+use glium::{
+    backend::Facade,
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerBehavior, SamplerWrapFunction},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFilter {
+    Linear,
+    Nearest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureWrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+/// How a texture is sampled when drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureSettings {
+    pub filter: TextureFilter,
+    pub wrap: TextureWrap,
+}
+
+impl Default for TextureSettings {
+    fn default() -> Self {
+        Self {
+            filter: TextureFilter::Linear,
+            wrap: TextureWrap::Repeat,
+        }
+    }
+}
+
+impl TextureSettings {
+    pub fn sampler_behavior(&self) -> SamplerBehavior {
+        let (minify_filter, magnify_filter) = match self.filter {
+            TextureFilter::Linear => (
+                MinifySamplerFilter::LinearMipmapLinear,
+                MagnifySamplerFilter::Linear,
+            ),
+            TextureFilter::Nearest => (MinifySamplerFilter::Nearest, MagnifySamplerFilter::Nearest),
+        };
+        let wrap = match self.wrap {
+            TextureWrap::Repeat => SamplerWrapFunction::Repeat,
+            TextureWrap::MirroredRepeat => SamplerWrapFunction::Mirror,
+            TextureWrap::ClampToEdge => SamplerWrapFunction::Clamp,
+        };
+        SamplerBehavior {
+            minify_filter,
+            magnify_filter,
+            wrap_function: (wrap, wrap, wrap),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Decode(image::ImageError),
+    /// The pixels passed to `from_rgba` don't have 4 bytes for every pixel.
+    SizeMismatch {
+        width: u32,
+        height: u32,
+        len: usize,
+    },
+    Create(glium::texture::TextureCreationError),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::Decode(why) => write!(f, "could not decode image: {}", why),
+            TextureError::SizeMismatch { width, height, len } => write!(
+                f,
+                "{} bytes of pixels don't fit a {}x{} RGBA texture",
+                len, width, height
+            ),
+            TextureError::Create(why) => write!(f, "could not create texture: {:?}", why),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+impl From<image::ImageError> for TextureError {
+    fn from(why: image::ImageError) -> Self {
+        TextureError::Decode(why)
+    }
+}
+
+impl From<glium::texture::TextureCreationError> for TextureError {
+    fn from(why: glium::texture::TextureCreationError) -> Self {
+        TextureError::Create(why)
+    }
+}
+
+/// An RGBA texture on the GPU. Cloning is cheap and shares the same texture.
+#[derive(Clone)]
+pub struct Texture2D {
+    texture: Rc<glium::texture::SrgbTexture2d>,
+    settings: TextureSettings,
+}
+
+impl Texture2D {
+    /// Loads a PNG or JPEG from disk.
+    pub fn from_file(
+        display: &impl Facade,
+        path: impl AsRef<Path>,
+        settings: TextureSettings,
+    ) -> Result<Self, TextureError> {
+        let image = image::open(path)?.to_rgba8();
+        let (width, height) = image.dimensions();
+        Self::from_rgba(display, width, height, &image.into_raw(), settings)
+    }
+
+    /// Loads a PNG or JPEG that is already in memory, like from `include_bytes!`.
+    pub fn from_memory(
+        display: &impl Facade,
+        bytes: &[u8],
+        settings: TextureSettings,
+    ) -> Result<Self, TextureError> {
+        let image = image::load_from_memory(bytes)?.to_rgba8();
+        let (width, height) = image.dimensions();
+        Self::from_rgba(display, width, height, &image.into_raw(), settings)
+    }
+
+    /// Creates a texture from tightly packed RGBA8 pixels, top row first.
+    /// `pixels` must hold exactly `width * height * 4` bytes.
+    pub fn from_rgba(
+        display: &impl Facade,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+        settings: TextureSettings,
+    ) -> Result<Self, TextureError> {
+        check_rgba_size(width, height, pixels)?;
+        let image = glium::texture::RawImage2d::from_raw_rgba_reversed(pixels, (width, height));
+        let texture = glium::texture::SrgbTexture2d::new(display, image)?;
+        Ok(Self {
+            texture: Rc::new(texture),
+            settings,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.texture.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.texture.height()
+    }
+
+    pub fn settings(&self) -> TextureSettings {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: TextureSettings) {
+        self.settings = settings;
+    }
+
+    pub fn texture(&self) -> &glium::texture::SrgbTexture2d {
+        &self.texture
+    }
+}
+
+impl PartialEq for Texture2D {
+    /// Textures are equal when they share the same GPU texture and settings.
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.texture, &other.texture) && self.settings == other.settings
+    }
+}
+
+fn check_rgba_size(width: u32, height: u32, pixels: &[u8]) -> Result<(), TextureError> {
+    // u128, so the largest textures can't overflow
+    let expected = width as u128 * height as u128 * 4;
+    if pixels.len() as u128 != expected {
+        return Err(TextureError::SizeMismatch {
+            width,
+            height,
+            len: pixels.len(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgba_pixels_must_fill_the_texture() {
+        assert!(check_rgba_size(2, 3, &[0; 24]).is_ok());
+        assert!(check_rgba_size(0, 0, &[]).is_ok());
+
+        for len in [23, 25, 6] {
+            match check_rgba_size(2, 3, &vec![0; len]) {
+                Err(TextureError::SizeMismatch {
+                    width: 2,
+                    height: 3,
+                    len: reported,
+                }) => assert_eq!(reported, len),
+                other => panic!(
+                    "expected a size mismatch for {} bytes, got {:?}",
+                    len, other
+                ),
+            }
+        }
+        assert!(matches!(
+            check_rgba_size(u32::MAX, u32::MAX, &[0; 4]),
+            Err(TextureError::SizeMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,264 @@
+use std::time::{Duration, Instant};
+
+/// Time information for one update: how long the last frame took and how long
+/// the application has been running. Multiply speeds by `seconds()` to make
+/// movement independent of the frame rate.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Timestep {
+    delta: f32,
+    elapsed: f64,
+    alpha: f32,
+    fps: f32,
+}
+
+impl Timestep {
+    pub fn new(delta: f32, elapsed: f64) -> Self {
+        Self {
+            delta,
+            elapsed,
+            alpha: 0.0,
+            fps: 0.0,
+        }
+    }
+
+    pub fn with_alpha(mut self, alpha: f32) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Time since the previous update, in seconds.
+    pub fn seconds(&self) -> f32 {
+        self.delta
+    }
+
+    pub fn milliseconds(&self) -> f32 {
+        self.delta * 1000.0
+    }
+
+    /// Time since the application started, in seconds. In `on_fixed_update`
+    /// this is the simulated time, which only advances in whole steps.
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// How far rendering is between the last fixed update and the next one,
+    /// in `0.0..1.0`. Draw fixed-rate state as
+    /// `previous.lerp(current, timestep.alpha())` to keep it smooth. Always
+    /// `0.0` inside `on_fixed_update`.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Frames actually drawn per second, counted over the last second. Always
+    /// `0.0` inside `on_fixed_update`.
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+}
+
+/// Measures the time between calls to `tick`, and how many ticks happen per
+/// second.
+pub struct Clock {
+    start: Instant,
+    last_tick: Instant,
+    counting_since: Instant,
+    ticks_counted: u32,
+    fps: f32,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            last_tick: now,
+            counting_since: now,
+            ticks_counted: 0,
+            fps: 0.0,
+        }
+    }
+
+    /// Returns the time since the previous tick, or since the clock was
+    /// created for the first tick.
+    pub fn tick(&mut self) -> Timestep {
+        let now = Instant::now();
+        let delta = now.saturating_duration_since(self.last_tick);
+        self.last_tick = now;
+
+        self.ticks_counted += 1;
+        let counted = now.saturating_duration_since(self.counting_since);
+        if counted >= Duration::from_secs(1) {
+            self.fps = self.ticks_counted as f32 / counted.as_secs_f32();
+            self.counting_since = now;
+            self.ticks_counted = 0;
+        }
+
+        let mut timestep = Timestep::new(
+            delta.as_secs_f32(),
+            now.saturating_duration_since(self.start).as_secs_f64(),
+        );
+        timestep.fps = self.fps;
+        timestep
+    }
+
+    /// Measures the next tick from now, so time spent paused doesn't show up
+    /// as one very long frame.
+    pub fn resume(&mut self) {
+        let now = Instant::now();
+        self.last_tick = now;
+        self.counting_since = now;
+        self.ticks_counted = 0;
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Turns variable frame times into a whole number of fixed-size simulation
+/// steps, carrying the leftover time over to the next frame.
+///
+/// ```ignore
+/// fixed.accumulate(frame_time);
+/// while let Some(timestep) = fixed.next_step() {
+///     simulate(timestep);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    step: Duration,
+    max_steps_per_frame: u32,
+    accumulator: Duration,
+    steps_this_frame: u32,
+    steps: u64,
+}
+
+impl FixedTimestep {
+    /// The slowest rate, in steps per second. Slower rates and NaN are
+    /// clamped to it.
+    pub const MIN_RATE: f64 = 1.0;
+    /// The fastest rate, in steps per second. Faster rates are clamped to it.
+    pub const MAX_RATE: f64 = 10_000.0;
+
+    /// `rate` is in steps per second, between `MIN_RATE` and `MAX_RATE`.
+    pub fn new(rate: f64) -> Self {
+        Self {
+            step: step_for_rate(rate),
+            max_steps_per_frame: 5,
+            accumulator: Duration::ZERO,
+            steps_this_frame: 0,
+            steps: 0,
+        }
+    }
+
+    pub fn set_rate(&mut self, rate: f64) {
+        self.step = step_for_rate(rate);
+    }
+
+    /// Limits how many steps a single frame catches up on. When updates take
+    /// longer than the time they simulate, each frame would otherwise have to
+    /// run even more of them. Time beyond the limit is dropped, so the
+    /// simulation slows down instead.
+    pub fn set_max_steps_per_frame(&mut self, max_steps_per_frame: u32) {
+        self.max_steps_per_frame = max_steps_per_frame.max(1);
+    }
+
+    /// Adds the time of a new frame.
+    pub fn accumulate(&mut self, delta: Duration) {
+        self.accumulator += delta;
+        self.steps_this_frame = 0;
+    }
+
+    /// Returns the timestep of the next step to run this frame, if any.
+    pub fn next_step(&mut self) -> Option<Timestep> {
+        if self.accumulator < self.step {
+            return None;
+        }
+        if self.steps_this_frame == self.max_steps_per_frame {
+            debug!(
+                "Fixed update is falling behind, dropping {:.1} ms",
+                (self.accumulator - self.step).as_secs_f64() * 1000.0
+            );
+            // keep the fraction of a step, so alpha stays continuous
+            self.accumulator =
+                Duration::from_secs_f64(self.accumulator.as_secs_f64() % self.step.as_secs_f64());
+            return None;
+        }
+        self.accumulator -= self.step;
+        self.steps_this_frame += 1;
+        self.steps += 1;
+        Some(Timestep::new(
+            self.step.as_secs_f32(),
+            self.steps as f64 * self.step.as_secs_f64(),
+        ))
+    }
+
+    /// Leftover time as a fraction of a step.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.step.as_secs_f64()) as f32
+    }
+}
+
+fn step_for_rate(rate: f64) -> Duration {
+    let clamped = if rate.is_nan() {
+        FixedTimestep::MIN_RATE
+    } else {
+        rate.clamp(FixedTimestep::MIN_RATE, FixedTimestep::MAX_RATE)
+    };
+    if clamped != rate {
+        warn!(
+            "Fixed update rate {} is out of range, using {}",
+            rate, clamped
+        );
+    }
+    Duration::from_secs_f64(1.0 / clamped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_frame(fixed: &mut FixedTimestep, delta: Duration) -> Vec<Timestep> {
+        fixed.accumulate(delta);
+        std::iter::from_fn(|| fixed.next_step()).collect()
+    }
+
+    #[test]
+    fn fixed_timestep_carries_leftover_time() {
+        let mut fixed = FixedTimestep::new(100.0);
+        let steps = run_frame(&mut fixed, Duration::from_millis(25));
+        assert_eq!(steps.len(), 2);
+        assert!((steps[0].seconds() - 0.01).abs() < 1e-6);
+        assert!((steps[0].elapsed() - 0.01).abs() < 1e-9);
+        assert!((steps[1].elapsed() - 0.02).abs() < 1e-9);
+        assert!((fixed.alpha() - 0.5).abs() < 1e-4);
+
+        let steps = run_frame(&mut fixed, Duration::from_millis(5));
+        assert_eq!(steps.len(), 1);
+        assert!(fixed.alpha() < 1e-4);
+    }
+
+    #[test]
+    fn fixed_timestep_caps_catch_up() {
+        let mut fixed = FixedTimestep::new(100.0);
+        fixed.set_max_steps_per_frame(3);
+        assert_eq!(run_frame(&mut fixed, Duration::from_millis(1005)).len(), 3);
+        assert!((fixed.alpha() - 0.5).abs() < 1e-3);
+        // the dropped time is not run on the next frame
+        assert!(run_frame(&mut fixed, Duration::ZERO).is_empty());
+    }
+
+    #[test]
+    fn fixed_timestep_clamps_rate() {
+        let mut fixed = FixedTimestep::new(0.0);
+        assert_eq!(run_frame(&mut fixed, Duration::from_millis(1500)).len(), 1);
+        fixed.set_rate(f64::NAN);
+        assert_eq!(fixed.step, Duration::from_secs(1));
+        fixed.set_rate(-5.0);
+        assert_eq!(fixed.step, Duration::from_secs(1));
+        fixed.set_rate(f64::INFINITY);
+        assert_eq!(fixed.step, Duration::from_micros(100));
+    }
+}